@@ -1,10 +1,24 @@
-use std::convert::{TryFrom, TryInto};
+use std::convert::TryInto;
+use crate::game::position::Position;
+use crate::game::packets::Slot;
+use crate::game::nbt::NBTTag;
+use uuid::Uuid;
 
 pub struct DataReader<'a> {
     pub data: &'a [u8],
     pub cursor: usize
 }
 
+//Every read checks the remaining length first, so truncated packets from malicious clients error instead of panicking
+pub const EOF: &str = "Unexpected end of packet";
+pub const STRING_TOO_LONG: &str = "String is too long";
+pub const NEGATIVE_LENGTH: &str = "Negative length";
+pub const VARINT_TOO_BIG: &str = "VarInt is too big";
+pub const VARLONG_TOO_BIG: &str = "VarLong is too big";
+
+//Biggest string length in bytes accepted by default, same as vanilla
+pub const MAX_STRING_LENGTH: usize = 32767;
+
 impl DataReader<'_> {
     pub fn new(data: &[u8]) -> DataReader {
         DataReader { data, cursor: 0 }
@@ -13,103 +27,416 @@ impl DataReader<'_> {
         DataReader {data, cursor}
     }
 
-    pub fn read_data_fixed<'a>(&mut self, length: usize) -> Option<Vec<u8>> {
-        if !self.check_lenght(length) {return None}
+    pub fn read_data_fixed<'a>(&mut self, length: usize) -> Result<Vec<u8>, &'static str> {
+        self.check_lenght(length)?;
 
-        let mut data = &self.data[self.cursor..self.cursor + length];
+        let data = &self.data[self.cursor..self.cursor + length];
 
         self.cursor += length;
-        Some(data.to_vec())
+        Ok(data.to_vec())
     }
 
-    pub fn read_data(&mut self) -> Option<Vec<u8>> {
+    pub fn read_data(&mut self) -> Result<Vec<u8>, &'static str> {
         let length = self.read_varint()?;
+        if length < 0 {return Err(NEGATIVE_LENGTH)}
 
         self.read_data_fixed(length as usize)
     }
 
-    pub fn read_varint<'a>(&mut self) -> Option<i32> {
+    //Everything left in the packet, empty if it was already fully read
+    pub fn read_remaining<'a>(&mut self) -> Vec<u8> {
+        let data = self.data[self.cursor.min(self.data.len())..].to_vec();
+        self.cursor = self.data.len();
+        data
+    }
+
+    pub fn read_varint<'a>(&mut self) -> Result<i32, &'static str> {
         let mut result: i32 = 0;
         let mut num_read: u8 = 0;
         let mut read: u8;
 
         loop {
+            //Checked before shifting, a 6th byte would shift past the 32 bits
+            if num_read >= 5 {return Err(VARINT_TOO_BIG)}
             read = self.read_u8()?;
-            result += (((read as i8) & 0b01111111) as i32) << (7 * num_read);
+            result |= (((read as i8) & 0b01111111) as i32) << (7 * num_read);
 
             num_read += 1;
-            if num_read > 5 {return None}
-            if (read & 0b10000000) == 0 {return Some(result)}
+            if (read & 0b10000000) == 0 {return Ok(result)}
         }
     }
 
-    pub fn read_varlong<'a>(&mut self) -> Option<i64> {
+    pub fn read_varlong<'a>(&mut self) -> Result<i64, &'static str> {
         let mut result: i64 = 0;
         let mut num_read: u8 = 0;
         let mut read: u8;
 
         loop {
+            //Checked before shifting, an 11th byte would shift past the 64 bits
+            if num_read >= 10 {return Err(VARLONG_TOO_BIG)}
             read = self.read_u8()?;
-            result += ((read & 0b01111111) as i64) << (7 * num_read);
+            result |= ((read & 0b01111111) as i64) << (7 * num_read);
 
             num_read += 1;
-            if num_read > 10 {return None}
-            if (read & 0b10000000) == 0 {return Some(result)}
+            if (read & 0b10000000) == 0 {return Ok(result)}
         }
     }
 
     #[inline]
-    pub fn read_u8<'a>(&mut self) -> Option<u8> {
-        if !self.check_lenght(1) {
-            return None
-        }
+    pub fn read_u8<'a>(&mut self) -> Result<u8, &'static str> {
+        self.check_lenght(1)?;
 
         self.cursor += 1;
 
-        return Some(self.data[self.cursor - 1]);
+        return Ok(self.data[self.cursor - 1]);
     }
 
-    pub fn read_u16<'a>(&mut self) -> Option<u16> {
-        if !self.check_lenght(2) {
-            return None;
-        }
+    #[inline]
+    pub fn read_i8<'a>(&mut self) -> Result<i8, &'static str> {
+        Ok(self.read_u8()? as i8)
+    }
+
+    #[inline]
+    pub fn read_bool<'a>(&mut self) -> Result<bool, &'static str> {
+        Ok(self.read_u8()? != 0x00)
+    }
+
+    pub fn read_u16<'a>(&mut self) -> Result<u16, &'static str> {
+        self.check_lenght(2)?;
 
-        let n = u16::from_be_bytes(match self.data[self.cursor..self.cursor + 2].try_into() {Ok(t) => t, Err(_e) => return None});
+        let n = u16::from_be_bytes(self.data[self.cursor..self.cursor + 2].try_into().map_err(|_e| EOF)?);
 
         self.cursor += 2;
-        return Some(n);
+        return Ok(n);
+    }
+
+    pub fn read_i16<'a>(&mut self) -> Result<i16, &'static str> {
+        self.check_lenght(2)?;
+
+        let n = i16::from_be_bytes(self.data[self.cursor..self.cursor + 2].try_into().map_err(|_e| EOF)?);
+
+        self.cursor += 2;
+        return Ok(n);
+    }
+
+    pub fn read_i64<'a>(&mut self) -> Result<i64, &'static str> {
+        self.check_lenght(8)?;
+
+        let n = i64::from_be_bytes(self.data[self.cursor..self.cursor + 8].try_into().map_err(|_e| EOF)?);
+
+        self.cursor += 8;
+        return Ok(n);
+    }
+
+    pub fn read_fixed_point_i32<'a>(&mut self) -> Result<f64, &'static str> {
+        Ok(self.read_i32()? as f64 / 32.0)
     }
 
-    pub fn read_i64<'a>(&mut self) -> Option<i64>{
-        if !self.check_lenght(8) {
-            return None;
+    pub fn read_fixed_point_i8<'a>(&mut self) -> Result<f64, &'static str> {
+        Ok(self.read_i8()? as f64 / 32.0)
+    }
+
+    //Angle in degrees, from 0 to 360
+    pub fn read_angle<'a>(&mut self) -> Result<f32, &'static str> {
+        Ok(self.read_u8()? as f32 * 360.0 / 256.0)
+    }
+
+    pub fn read_position<'a>(&mut self) -> Result<Position, &'static str> {
+        Ok(Position::decode(self.read_i64()?))
+    }
+
+    //Play packets send the UUID as 16 raw bytes instead of the hyphenated string used in the login
+    pub fn read_uuid<'a>(&mut self) -> Result<Uuid, &'static str> {
+        self.check_lenght(16)?;
+
+        let uuid = Uuid::from_slice(&self.data[self.cursor..self.cursor + 16]).map_err(|_e| EOF)?;
+
+        self.cursor += 16;
+        return Ok(uuid);
+    }
+
+    pub fn read_slot<'a>(&mut self) -> Result<Slot, &'static str> {
+        let item_id = self.read_i16()?;
+        if item_id < 0 {
+            return Ok(Slot::empty());
         }
 
-        let n = i64::from_be_bytes(match self.data[self.cursor..self.cursor + 8].try_into() {Ok(t) => t, Err(_e) => return None});
+        Ok(Slot {
+            item_id,
+            item_count: Some(self.read_i8()?),
+            item_damage: Some(self.read_i16()?),
+            nbt: NBTTag::read(self)?
+        })
+    }
+
+    pub fn read_i32<'a>(&mut self) -> Result<i32, &'static str> {
+        self.check_lenght(4)?;
+
+        let n = i32::from_be_bytes(self.data[self.cursor..self.cursor + 4].try_into().map_err(|_e| EOF)?);
+
+        self.cursor += 4;
+        return Ok(n);
+    }
+
+    pub fn read_f32<'a>(&mut self) -> Result<f32, &'static str> {
+        self.check_lenght(4)?;
+
+        let n = f32::from_be_bytes(self.data[self.cursor..self.cursor + 4].try_into().map_err(|_e| EOF)?);
+
+        self.cursor += 4;
+        return Ok(n);
+    }
+
+    pub fn read_f64<'a>(&mut self) -> Result<f64, &'static str> {
+        self.check_lenght(8)?;
+
+        let n = f64::from_be_bytes(self.data[self.cursor..self.cursor + 8].try_into().map_err(|_e| EOF)?);
 
         self.cursor += 8;
-        return Some(n);
+        return Ok(n);
+    }
+
+    #[inline]
+    pub fn read_string<'a>(&mut self) -> Result<String, &'static str> {
+        self.read_string_limited(MAX_STRING_LENGTH)
     }
 
-    pub fn read_string<'a>(&mut self) -> Option<String> {
-        let string_length = self.read_varint()? as usize;
+    //The length is checked before allocating, so a client can't make the server allocate a huge string
+    pub fn read_string_limited<'a>(&mut self, max_length: usize) -> Result<String, &'static str> {
+        let string_length = self.read_varint()?;
+        if string_length < 0 {return Err(NEGATIVE_LENGTH)}
+        let string_length = string_length as usize;
+        if string_length > max_length {return Err(STRING_TOO_LONG)}
 
-        if string_length == 0 {return Some(String::new());}
+        if string_length == 0 {return Ok(String::new());}
 
-        if !self.check_lenght(string_length) {return None}
+        self.check_lenght(string_length)?;
 
         let vec = self.data[self.cursor..string_length + self.cursor].to_vec();
 
         self.cursor += string_length;
 
-        return match String::from_utf8(vec) {Ok(t) => Some(t),Err(_e) => None};
+        return String::from_utf8(vec).map_err(|_e| "Invalid UTF-8 string");
     }
 
     #[inline]
-    fn check_lenght(&self, lenght: usize) -> bool {
-        if lenght + self.cursor > self.data.len() {
-            return false;
+    fn check_lenght(&self, lenght: usize) -> Result<(), &'static str> {
+        //Written this way so a huge length can't overflow
+        if self.cursor > self.data.len() || lenght > self.data.len() - self.cursor {
+            return Err(EOF);
         }
-        return true;
+        return Ok(());
+    }
+}
+
+#[test]
+pub fn test_read_position() {
+    use crate::data_writer::DataWriter;
+
+    let positions = [
+        Position {x: 0, y: 0, z: 0},
+        Position {x: 18357644, y: 831, z: -20882616},
+        Position {x: -1, y: -1, z: -1},
+        Position {x: -33554432, y: -2048, z: 33554431}
+    ];
+
+    for position in positions.iter() {
+        let mut writer = DataWriter::new();
+        writer.write_position(position);
+        assert_eq!(DataReader::new(&writer.data).read_position().unwrap(), *position);
+    }
+}
+
+#[test]
+pub fn test_read_primitives() {
+    use crate::data_writer::DataWriter;
+
+    let mut writer = DataWriter::new();
+    writer.write_bool(true);
+    writer.write_bool(false);
+    writer.write_i8(-42);
+    writer.write_i32(-123456789);
+    writer.write_f32(-1.5);
+    writer.write_f64(64.015625);
+
+    let mut reader = DataReader::new(&writer.data);
+    assert_eq!(reader.read_bool(), Ok(true));
+    assert_eq!(reader.read_bool(), Ok(false));
+    assert_eq!(reader.read_i8(), Ok(-42));
+    assert_eq!(reader.read_i32(), Ok(-123456789));
+    assert_eq!(reader.read_f32(), Ok(-1.5));
+    assert_eq!(reader.read_f64(), Ok(64.015625));
+    assert!(reader.read_u8().is_err());
+}
+
+#[test]
+pub fn test_read_remaining() {
+    use crate::data_writer::DataWriter;
+
+    let mut writer = DataWriter::new();
+    writer.write_string(&"MC|Brand".to_string());
+    writer.write_data(&[1, 2, 3]);
+
+    let mut reader = DataReader::new(&writer.data);
+    assert_eq!(reader.read_string().unwrap(), "MC|Brand");
+    assert_eq!(reader.read_remaining(), vec![1, 2, 3]);
+    //Already exhausted
+    assert_eq!(reader.read_remaining(), Vec::<u8>::new());
+}
+
+#[test]
+pub fn test_short_buffers() {
+    let empty: [u8; 0] = [];
+    assert_eq!(DataReader::new(&empty).read_u8(), Err(EOF));
+    assert_eq!(DataReader::new(&empty).read_bool(), Err(EOF));
+    assert_eq!(DataReader::new(&[0x01]).read_u16(), Err(EOF));
+    assert_eq!(DataReader::new(&[0x01, 0x02, 0x03]).read_i32(), Err(EOF));
+    assert_eq!(DataReader::new(&[0x01, 0x02, 0x03]).read_f32(), Err(EOF));
+    assert_eq!(DataReader::new(&[0x01; 7]).read_i64(), Err(EOF));
+    assert_eq!(DataReader::new(&[0x01; 7]).read_f64(), Err(EOF));
+    assert_eq!(DataReader::new(&[0x01; 7]).read_position(), Err(EOF));
+    assert_eq!(DataReader::new(&[0x01, 0x02]).read_data_fixed(3), Err(EOF));
+    //Length says 5 but only 2 bytes follow
+    assert_eq!(DataReader::new(&[0x05, 0x01, 0x02]).read_data(), Err(EOF));
+    assert_eq!(DataReader::new(&[0x05, b'a', b'b']).read_string(), Err(EOF));
+    //VarInt with the continue bit set but no next byte
+    assert_eq!(DataReader::new(&[0x80]).read_varint(), Err(EOF));
+    assert_eq!(DataReader::new(&[0xFF, 0xFF]).read_varlong(), Err(EOF));
+    //Failed reads don't move the cursor past the end
+    let mut reader = DataReader::new(&[0x01, 0x02]);
+    assert!(reader.read_i32().is_err());
+    assert_eq!(reader.read_u16(), Ok(0x0102));
+}
+
+#[test]
+pub fn test_string_limit() {
+    use crate::data_writer::DataWriter;
+
+    //Declares a 100000 bytes string without sending it, the length alone must be rejected
+    let mut writer = DataWriter::new();
+    writer.write_varint(100000);
+    assert_eq!(DataReader::new(&writer.data).read_string(), Err(STRING_TOO_LONG));
+
+    let mut writer = DataWriter::new();
+    writer.write_string(&"localhost".to_string());
+    assert_eq!(DataReader::new(&writer.data).read_string_limited(9), Ok("localhost".to_string()));
+    assert_eq!(DataReader::new(&writer.data).read_string_limited(8), Err(STRING_TOO_LONG));
+}
+
+#[test]
+pub fn test_read_slot() {
+    use crate::data_writer::DataWriter;
+
+    let slots = [
+        Slot::empty(),
+        Slot {item_id: 1, item_count: Some(64), item_damage: Some(0), nbt: None},
+        //Diamond sword with some durability used
+        Slot {item_id: 276, item_count: Some(1), item_damage: Some(120), nbt: None}
+    ];
+    for slot in slots.iter() {
+        let mut writer = DataWriter::new();
+        writer.write_slot(slot);
+        let mut reader = DataReader::new(&writer.data);
+        assert_eq!(reader.read_slot().unwrap(), *slot);
+        assert_eq!(reader.cursor, writer.data.len());
     }
+
+    assert_eq!(DataReader::new(&[0xFF, 0xFF]).read_slot().unwrap(), Slot::empty());
+    assert_eq!(DataReader::new(&[0x00, 0x01, 0x40, 0x00]).read_slot(), Err(EOF));
+}
+
+#[test]
+pub fn test_read_uuid() {
+    use crate::data_writer::DataWriter;
+
+    let uuid = Uuid::new_v4();
+    let mut writer = DataWriter::new();
+    writer.write_uuid(&uuid);
+    assert_eq!(writer.data.len(), 16);
+    assert_eq!(DataReader::new(&writer.data).read_uuid(), Ok(uuid));
+    assert_eq!(DataReader::new(&writer.data[0..15]).read_uuid(), Err(EOF));
+}
+
+#[test]
+pub fn test_varlong() {
+    use crate::data_writer::DataWriter;
+
+    for (value, length) in [(0i64, 1usize), (1 << 40, 6), (i64::MAX, 9), (-1, 10)].iter() {
+        let mut writer = DataWriter::new();
+        writer.write_varlong(*value);
+        assert_eq!(writer.data.len(), *length);
+        assert_eq!(DataReader::new(&writer.data).read_varlong(), Ok(*value));
+    }
+
+    //Negative varints are 5 bytes, not sign extended to 10
+    let mut writer = DataWriter::new();
+    writer.write_varint(-1);
+    assert_eq!(writer.data, vec![0xFF, 0xFF, 0xFF, 0xFF, 0x0F]);
+    assert_eq!(DataReader::new(&writer.data).read_varint(), Ok(-1));
+
+    assert_eq!(DataReader::new(&[0xFF; 11]).read_varlong(), Err(VARLONG_TOO_BIG));
+    assert_eq!(DataReader::new(&[0xFF; 6]).read_varint(), Err(VARINT_TOO_BIG));
+}
+
+#[test]
+pub fn test_read_angle() {
+    use crate::data_writer::DataWriter;
+
+    for degrees in [0.0f32, 90.0, 180.0, 270.0, 45.7].iter() {
+        let mut writer = DataWriter::new();
+        writer.write_angle(*degrees);
+        let angle = DataReader::new(&writer.data).read_angle().unwrap();
+        assert!((angle - degrees).abs() <= 360.0 / 256.0);
+    }
+
+    //Negative and bigger than a turn angles wrap around
+    let mut writer = DataWriter::new();
+    writer.write_angle(-90.0);
+    writer.write_angle(450.0);
+    writer.write_angle(359.9);
+    assert_eq!(writer.data, vec![192, 64, 0]);
+}
+
+#[test]
+pub fn test_read_fixed_point() {
+    use crate::data_writer::DataWriter;
+    use crate::game::position::relative_delta;
+
+    let mut writer = DataWriter::new();
+    writer.write_fixed_point_i32(10.5);
+    writer.write_fixed_point_i32(-64.25);
+    writer.write_i8(relative_delta(1.5).unwrap());
+    writer.write_i8(relative_delta(-0.75).unwrap());
+    writer.write_i8(relative_delta(3.96875).unwrap());
+    writer.write_i8(relative_delta(-4.0).unwrap());
+
+    let mut reader = DataReader::new(&writer.data);
+    assert_eq!(reader.read_fixed_point_i32(), Ok(10.5));
+    assert_eq!(reader.read_fixed_point_i32(), Ok(-64.25));
+    assert_eq!(reader.read_fixed_point_i8(), Ok(1.5));
+    assert_eq!(reader.read_fixed_point_i8(), Ok(-0.75));
+    assert_eq!(reader.read_fixed_point_i8(), Ok(3.96875));
+    assert_eq!(reader.read_fixed_point_i8(), Ok(-4.0));
+}
+
+#[test]
+pub fn test_write_relative_delta() {
+    use crate::data_writer::DataWriter;
+
+    let mut writer = DataWriter::new();
+    writer.write_relative_delta(1.5);
+    writer.write_relative_delta(-0.75);
+    writer.write_relative_delta(3.9375);
+    writer.write_relative_delta(-3.9375);
+    //Out of range deltas are clamped
+    writer.write_relative_delta(10.0);
+    writer.write_relative_delta(-4.0);
+
+    let mut reader = DataReader::new(&writer.data);
+    assert_eq!(reader.read_fixed_point_i8(), Ok(1.5));
+    assert_eq!(reader.read_fixed_point_i8(), Ok(-0.75));
+    assert_eq!(reader.read_fixed_point_i8(), Ok(3.9375));
+    assert_eq!(reader.read_fixed_point_i8(), Ok(-3.9375));
+    assert_eq!(reader.read_fixed_point_i8(), Ok(3.9375));
+    assert_eq!(reader.read_fixed_point_i8(), Ok(-3.9375));
 }
\ No newline at end of file
@@ -1,4 +1,6 @@
 use crate::game::position::Position;
+use crate::game::packets::Slot;
+use uuid::Uuid;
 use arrayvec::ArrayVec;
 
 pub struct DataWriter {
@@ -16,7 +18,8 @@ impl DataWriter {
     }
 
     pub fn write_varint(&mut self, mut value: i32) {
-        self.data.extend_from_slice(DataWriter::var_num(value as u64).as_slice());
+        //Through u32 so negative values aren't sign extended to 64 bits
+        self.data.extend_from_slice(DataWriter::var_num(value as u32 as u64).as_slice());
     }
 
     pub fn write_varlong(&mut self, mut value: i64) {
@@ -75,6 +78,39 @@ impl DataWriter {
         self.data.extend_from_slice(&position.encode().to_be_bytes());
     }
 
+    //Entity coordinates are fixed-point, the block value * 32, floored like vanilla
+    pub fn write_fixed_point_i32(&mut self, value: f64) {
+        self.write_i32((value * 32.0).floor() as i32);
+    }
+
+    //Relative moves are fixed-point bytes, deltas are clamped to the ±3.9375 blocks a byte can hold
+    //Use position::relative_delta first to know if the move needs an Entity Teleport instead
+    pub fn write_relative_delta(&mut self, delta: f64) {
+        self.write_i8((delta.max(-3.9375).min(3.9375) * 32.0).round() as i8);
+    }
+
+    //Angles are a byte with 1/256 of a full turn, any amount of degrees is wrapped to one turn
+    pub fn write_angle(&mut self, degrees: f32) {
+        self.write_u8((degrees.rem_euclid(360.0) * 256.0 / 360.0).round() as u32 as u8);
+    }
+
+    pub fn write_uuid(&mut self, uuid: &Uuid) {
+        self.data.extend_from_slice(uuid.as_bytes());
+    }
+
+    //Count, damage and NBT (a single 0 when there's none) only follow the id of non-empty slots
+    pub fn write_slot(&mut self, slot: &Slot) {
+        self.write_i16(slot.item_id);
+        if slot.item_id >= 0 {
+            self.write_i8(slot.item_count.unwrap_or(1));
+            self.write_i16(slot.item_damage.unwrap_or(0));
+            match &slot.nbt {
+                Some(nbt) => {nbt.write(&mut self.data, Some(&String::new()));}
+                None => self.write_u8(0)
+            }
+        }
+    }
+
     pub fn get_varint(value: u32) -> Vec<u8> {
         let mut vec = Vec::new();
         vec.extend_from_slice(DataWriter::var_num(value as u64).as_slice());
@@ -4,6 +4,7 @@ pub mod player_join;
 pub mod position;
 pub mod packets;
 pub mod nbt;
+pub mod metadata;
 pub mod world;
 pub mod player;
 pub mod ray_tracing;
@@ -1,23 +1,417 @@
 use json::JsonValue;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct ChatComponent {
-    json: JsonValue
+    text: String,
+    //When set the client renders the translation key in its own language instead of the text
+    translate: Option<String>,
+    //Arguments for the %s placeholders of the translation
+    with: Vec<ChatComponent>,
+    color: Option<String>,
+    //Styles are only sent when set, so unset ones are inherited from the parent component
+    bold: Option<bool>,
+    italic: Option<bool>,
+    underlined: Option<bool>,
+    strikethrough: Option<bool>,
+    obfuscated: Option<bool>,
+    click_event: Option<ClickEvent>,
+    hover_event: Option<HoverEvent>,
+    //Sent as the "extra" array, inheriting this component's style
+    siblings: Vec<ChatComponent>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClickEvent {
+    OpenUrl(String),
+    RunCommand(String),
+    SuggestCommand(String),
+    //Only used in written books
+    ChangePage(i32)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HoverEvent {
+    ShowText(Box<ChatComponent>),
+    //Item as a NBT string, e.g. {id:minecraft:stone,Count:1}
+    ShowItem(String)
+}
+
+impl ClickEvent {
+    pub fn from_json(json: &JsonValue) -> Result<ClickEvent, &'static str> {
+        let value = json["value"].as_str().ok_or("Click event value is not a string")?.to_owned();
+        match json["action"].as_str() {
+            Some("open_url") => Ok(ClickEvent::OpenUrl(value)),
+            Some("run_command") => Ok(ClickEvent::RunCommand(value)),
+            Some("suggest_command") => Ok(ClickEvent::SuggestCommand(value)),
+            Some("change_page") => value.parse().map(ClickEvent::ChangePage).map_err(|_| "Invalid page number"),
+            _ => Err("Unknown click event action")
+        }
+    }
+    pub fn to_json(&self) -> JsonValue {
+        let (action, value) = match self {
+            ClickEvent::OpenUrl(url) => ("open_url", url.clone()),
+            ClickEvent::RunCommand(command) => ("run_command", command.clone()),
+            ClickEvent::SuggestCommand(command) => ("suggest_command", command.clone()),
+            ClickEvent::ChangePage(page) => ("change_page", page.to_string())
+        };
+        let mut json = JsonValue::new_object();
+        json["action"] = JsonValue::String(action.to_owned());
+        json["value"] = JsonValue::String(value);
+        json
+    }
+}
+
+impl HoverEvent {
+    pub fn from_json(json: &JsonValue) -> Result<HoverEvent, &'static str> {
+        match json["action"].as_str() {
+            Some("show_text") => Ok(HoverEvent::ShowText(Box::new(ChatComponent::from_json(&json["value"])?))),
+            Some("show_item") => Ok(HoverEvent::ShowItem(json["value"].as_str().ok_or("Hover event value is not a string")?.to_owned())),
+            _ => Err("Unknown hover event action")
+        }
+    }
+    pub fn to_json(&self) -> JsonValue {
+        let mut json = JsonValue::new_object();
+        match self {
+            HoverEvent::ShowText(text) => {
+                json["action"] = JsonValue::String("show_text".to_owned());
+                json["value"] = text.to_json();
+            }
+            HoverEvent::ShowItem(item) => {
+                json["action"] = JsonValue::String("show_item".to_owned());
+                json["value"] = JsonValue::String(item.clone());
+            }
+        }
+        json
+    }
 }
 
 impl ChatComponent {
     pub fn new() -> ChatComponent {
-        ChatComponent {json: JsonValue::new_object()}
+        ChatComponent::new_text(String::new())
     }
     pub fn new_text(str: String) -> ChatComponent {
-        let mut json = JsonValue::new_object();
-        json["text"] = JsonValue::String(str);
-        ChatComponent {json}
+        ChatComponent {
+            text: str,
+            translate: None,
+            with: Vec::new(),
+            color: None,
+            bold: None,
+            italic: None,
+            underlined: None,
+            strikethrough: None,
+            obfuscated: None,
+            click_event: None,
+            hover_event: None,
+            siblings: Vec::new()
+        }
+    }
+
+    pub fn new_translate(key: &str, with: Vec<ChatComponent>) -> ChatComponent {
+        let mut component = ChatComponent::new();
+        component.translate = Some(key.to_owned());
+        component.with = with;
+        component
+    }
+
+    //Parses a string with legacy §/& formatting codes into a root component with a styled sibling per segment
+    //Color codes and §r reset the formatting, unknown codes are kept as plain text and a trailing § is dropped
+    pub fn from_legacy(legacy: &str) -> ChatComponent {
+        let mut root = ChatComponent::new();
+        let mut style = ChatComponent::new();
+        let mut text = String::new();
+        let mut chars = legacy.chars().peekable();
+
+        while let Some(char) = chars.next() {
+            if char == '§' || char == '&' {
+                let code = match chars.peek() {Some(t) => t.to_ascii_lowercase(), None => {
+                    //Unterminated code at the end
+                    if char == '&' {text.push(char)}
+                    break;
+                }};
+                let color = legacy_color(code);
+                if color.is_none() && !"klmnor".contains(code) {
+                    text.push(char);
+                    continue;
+                }
+                chars.next();
+
+                //Finish the segment with the style it had so far
+                if !text.is_empty() {
+                    let mut segment = style.clone();
+                    segment.text = std::mem::take(&mut text);
+                    root.siblings.push(segment);
+                }
+
+                match code {
+                    'k' => style.obfuscated = Some(true),
+                    'l' => style.bold = Some(true),
+                    'm' => style.strikethrough = Some(true),
+                    'n' => style.underlined = Some(true),
+                    'o' => style.italic = Some(true),
+                    'r' => style = ChatComponent::new(),
+                    _ => style = ChatComponent::new().color(color.unwrap())
+                }
+            } else {
+                text.push(char);
+            }
+        }
+
+        if !text.is_empty() {
+            style.text = text;
+            root.siblings.push(style);
+        }
+
+        root
+    }
+
+    //Reads a component sent by the client, a bare JSON string is a plain text component
+    pub fn from_json(json: &JsonValue) -> Result<ChatComponent, &'static str> {
+        if let Some(text) = json.as_str() {
+            return Ok(ChatComponent::new_text(text.to_owned()));
+        }
+        if !json.is_object() {
+            return Err("Chat component is not an object");
+        }
+
+        let mut component = ChatComponent::new();
+        if !json["text"].is_null() {
+            component.text = json["text"].as_str().ok_or("Chat text is not a string")?.to_owned();
+        }
+        if !json["translate"].is_null() {
+            component.translate = Some(json["translate"].as_str().ok_or("Chat translate is not a string")?.to_owned());
+        }
+        if !json["with"].is_null() {
+            if !json["with"].is_array() {
+                return Err("Chat with is not an array");
+            }
+            for argument in json["with"].members() {
+                component.with.push(ChatComponent::from_json(argument)?);
+            }
+        }
+        if !json["color"].is_null() {
+            component.color = Some(json["color"].as_str().ok_or("Chat color is not a string")?.to_owned());
+        }
+
+        let read_style = |name: &str| -> Result<Option<bool>, &'static str> {
+            if json[name].is_null() {
+                return Ok(None);
+            }
+            json[name].as_bool().map(Some).ok_or("Chat style is not a boolean")
+        };
+        component.bold = read_style("bold")?;
+        component.italic = read_style("italic")?;
+        component.underlined = read_style("underlined")?;
+        component.strikethrough = read_style("strikethrough")?;
+        component.obfuscated = read_style("obfuscated")?;
+
+        if !json["clickEvent"].is_null() {
+            component.click_event = Some(ClickEvent::from_json(&json["clickEvent"])?);
+        }
+        if !json["hoverEvent"].is_null() {
+            component.hover_event = Some(HoverEvent::from_json(&json["hoverEvent"])?);
+        }
+
+        if !json["extra"].is_null() {
+            if !json["extra"].is_array() {
+                return Err("Chat extra is not an array");
+            }
+            for sibling in json["extra"].members() {
+                component.siblings.push(ChatComponent::from_json(sibling)?);
+            }
+        }
+
+        Ok(component)
+    }
+
+    pub fn color(mut self, color: &str) -> ChatComponent {
+        self.color = Some(color.to_owned());
+        self
+    }
+    pub fn bold(mut self, bold: bool) -> ChatComponent {
+        self.bold = Some(bold);
+        self
+    }
+    pub fn italic(mut self, italic: bool) -> ChatComponent {
+        self.italic = Some(italic);
+        self
+    }
+    pub fn underlined(mut self, underlined: bool) -> ChatComponent {
+        self.underlined = Some(underlined);
+        self
     }
+    pub fn strikethrough(mut self, strikethrough: bool) -> ChatComponent {
+        self.strikethrough = Some(strikethrough);
+        self
+    }
+    pub fn obfuscated(mut self, obfuscated: bool) -> ChatComponent {
+        self.obfuscated = Some(obfuscated);
+        self
+    }
+
+    pub fn click_event(mut self, event: ClickEvent) -> ChatComponent {
+        self.click_event = Some(event);
+        self
+    }
+    pub fn hover_event(mut self, event: HoverEvent) -> ChatComponent {
+        self.hover_event = Some(event);
+        self
+    }
+
+    pub fn append(mut self, sibling: ChatComponent) -> ChatComponent {
+        self.siblings.push(sibling);
+        self
+    }
+
+    //Plain text of this component and its siblings, without any style
+    //Translations can't be resolved here so their key is used
     pub fn to_string(&self) -> String {
-        self.json.to_string()
+        let mut string = match &self.translate {
+            Some(key) => key.clone(),
+            None => self.text.clone()
+        };
+        for sibling in &self.siblings {
+            string.push_str(&sibling.to_string());
+        }
+        string
+    }
+    pub fn to_json_string(&self) -> String {
+        self.to_json().dump()
     }
-    pub fn to_json(self) -> JsonValue {
-        self.json
+    pub fn to_json(&self) -> JsonValue {
+        let mut json = JsonValue::new_object();
+        match &self.translate {
+            Some(key) => {
+                json["translate"] = JsonValue::String(key.clone());
+                if !self.with.is_empty() {
+                    json["with"] = JsonValue::Array(self.with.iter().map(|argument| argument.to_json()).collect());
+                }
+            }
+            None => json["text"] = JsonValue::String(self.text.clone())
+        }
+        if let Some(color) = &self.color {
+            json["color"] = JsonValue::String(color.clone());
+        }
+
+        let styles = [
+            ("bold", self.bold),
+            ("italic", self.italic),
+            ("underlined", self.underlined),
+            ("strikethrough", self.strikethrough),
+            ("obfuscated", self.obfuscated)
+        ];
+        for (name, value) in styles.iter() {
+            if let Some(value) = value {
+                json[*name] = JsonValue::Boolean(*value);
+            }
+        }
+
+        if let Some(event) = &self.click_event {
+            json["clickEvent"] = event.to_json();
+        }
+        if let Some(event) = &self.hover_event {
+            json["hoverEvent"] = event.to_json();
+        }
+
+        if !self.siblings.is_empty() {
+            json["extra"] = JsonValue::Array(self.siblings.iter().map(|sibling| sibling.to_json()).collect());
+        }
+
+        json
     }
+}
+
+fn legacy_color(code: char) -> Option<&'static str> {
+    Some(match code {
+        '0' => "black",
+        '1' => "dark_blue",
+        '2' => "dark_green",
+        '3' => "dark_aqua",
+        '4' => "dark_red",
+        '5' => "dark_purple",
+        '6' => "gold",
+        '7' => "gray",
+        '8' => "dark_gray",
+        '9' => "blue",
+        'a' => "green",
+        'b' => "aqua",
+        'c' => "red",
+        'd' => "light_purple",
+        'e' => "yellow",
+        'f' => "white",
+        _ => return None
+    })
+}
+
+#[test]
+pub fn test_chat_style() {
+    assert_eq!(ChatComponent::new_text("Hi".to_string()).to_json_string(), r#"{"text":"Hi"}"#);
+    assert_eq!(ChatComponent::new_text("Warning".to_string()).color("red").bold(true).to_json_string(),
+               r#"{"text":"Warning","color":"red","bold":true}"#);
+    assert_eq!(ChatComponent::new_text("x".to_string()).italic(false).underlined(true).strikethrough(true).obfuscated(true).to_json_string(),
+               r#"{"text":"x","italic":false,"underlined":true,"strikethrough":true,"obfuscated":true}"#);
+}
+
+#[test]
+pub fn test_chat_siblings() {
+    let component = ChatComponent::new_text("Hello ".to_string())
+        .append(ChatComponent::new_text("world".to_string()).bold(true));
+
+    assert_eq!(component.to_json_string(), r#"{"text":"Hello ","extra":[{"text":"world","bold":true}]}"#);
+    assert_eq!(component.to_string(), "Hello world");
+}
+
+#[test]
+pub fn test_chat_from_legacy() {
+    let colors = ChatComponent::from_legacy("§cRed §9Blue");
+    assert_eq!(colors.to_json_string(), r#"{"text":"","extra":[{"text":"Red ","color":"red"},{"text":"Blue","color":"blue"}]}"#);
+
+    //Formatting is kept until a color code, which resets it
+    let combo = ChatComponent::from_legacy("&6&lGold bold&e yellow");
+    assert_eq!(combo.to_json_string(), r#"{"text":"","extra":[{"text":"Gold bold","color":"gold","bold":true},{"text":" yellow","color":"yellow"}]}"#);
+
+    let reset = ChatComponent::from_legacy("§a§nHi§r there§");
+    assert_eq!(reset.to_json_string(), r#"{"text":"","extra":[{"text":"Hi","color":"green","underlined":true},{"text":" there"}]}"#);
+    assert_eq!(reset.to_string(), "Hi there");
+
+    assert_eq!(ChatComponent::from_legacy("Tom & Jerry").to_string(), "Tom & Jerry");
+}
+
+#[test]
+pub fn test_chat_events() {
+    let link = ChatComponent::new_text("Website".to_string())
+        .color("blue")
+        .underlined(true)
+        .click_event(ClickEvent::OpenUrl("https://example.com".to_string()))
+        .hover_event(HoverEvent::ShowText(Box::new(ChatComponent::new_text("Open the website".to_string()))));
+
+    assert_eq!(link.to_json_string(),
+               r#"{"text":"Website","color":"blue","underlined":true,"clickEvent":{"action":"open_url","value":"https://example.com"},"hoverEvent":{"action":"show_text","value":{"text":"Open the website"}}}"#);
+    assert_eq!(ClickEvent::ChangePage(2).to_json().dump(), r#"{"action":"change_page","value":"2"}"#);
+}
+
+#[test]
+pub fn test_chat_from_json() {
+    let component = ChatComponent::new_text("Hello ".to_string())
+        .color("gold")
+        .append(ChatComponent::new_text("world".to_string()).bold(true).click_event(ClickEvent::RunCommand("/spawn".to_string())));
+    let json = json::parse(&component.to_json_string()).unwrap();
+    let parsed = ChatComponent::from_json(&json).unwrap();
+    assert_eq!(parsed, component);
+    assert_eq!(parsed.to_json_string(), component.to_json_string());
+
+    let bare = ChatComponent::from_json(&json::parse(r#""plain""#).unwrap()).unwrap();
+    assert_eq!(bare, ChatComponent::new_text("plain".to_string()));
+    assert_eq!(bare.to_json_string(), r#"{"text":"plain"}"#);
+
+    assert!(ChatComponent::from_json(&json::parse(r#"{"text":"x","bold":"yes"}"#).unwrap()).is_err());
+    assert!(ChatComponent::from_json(&json::parse("5").unwrap()).is_err());
+}
+
+#[test]
+pub fn test_chat_translate() {
+    let joined = ChatComponent::new_translate("multiplayer.player.joined", vec![ChatComponent::new_text("Notch".to_string())])
+        .color("yellow");
+
+    assert_eq!(joined.to_json_string(), r#"{"translate":"multiplayer.player.joined","with":[{"text":"Notch"}],"color":"yellow"}"#);
+    assert_eq!(ChatComponent::from_json(&joined.to_json()).unwrap(), joined);
 }
\ No newline at end of file
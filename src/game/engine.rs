@@ -33,10 +33,10 @@ pub fn start(players: PlayerList, net_writer: NetWriter, game_reader: Receiver<G
                         let index = sync_environment.players.iter().position(|player| player.token.eq(&token));
                         match index {Some(t) => {
                             let player = sync_environment.players.remove(t);
-                            println!("Player {} disconnected", player.nickname)
+                            player_join::handle_quit(&player, &sync_environment.players, &net_writer);
                         }, None => {}};
                     }
-                    GameProtocol::Login {token, nickname, uuid} => {
+                    GameProtocol::Login {token, nickname, uuid, properties} => {
                         //Check if another player with the same UUID is already on the server
                         let already_logged_in = sync_environment.players.iter().any(|player| player.uuid.eq(&uuid));
                         if already_logged_in {
@@ -44,8 +44,8 @@ pub fn start(players: PlayerList, net_writer: NetWriter, game_reader: Receiver<G
                             continue;
                         }
 
-                        let mut player = Player {token, nickname, uuid};
-                        player_join::handle_join(&mut player, &net_writer);
+                        let mut player = Player {token, nickname, uuid, properties};
+                        player_join::handle_join(&mut player, &sync_environment.players, &net_writer);
                         sync_environment.players.push(player);
                     }
                     GameProtocol::Packet {token, packet} => {
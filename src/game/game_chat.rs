@@ -5,11 +5,11 @@ use crate::game::chat::ChatComponent;
 
 pub fn chat_listener(packet: &Packet, player_index: usize, environment: &mut SyncEnvironment) {
     match packet {
-        Packet::ClientChatMessage {message} => {
+        Packet::ServerChatMessage {message} => {
             // let name = environment.players[player_index].nickname.clone();
-            // let packet = Packet::ServerChatMessage {
+            // let packet = Packet::ChatMessage {
             //     component: ChatComponent::new_text(format!("§d{}: §f{}", name, message)),
-            //     pos: 0
+            //     position: 0
             // };
             // for x in environment.players.iter_mut() {
             //     x.connection.send_packet(&packet);
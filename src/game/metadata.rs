@@ -0,0 +1,103 @@
+use crate::data_writer::DataWriter;
+use crate::game::packets::Slot;
+
+//Entity metadata in the 1.8 format: each entry is a (type << 5 | index) byte followed by its value,
+//and the list ends with 0x7F
+pub struct EntityMetadata {
+    pub entries: Vec<MetadataEntry>
+}
+
+pub struct MetadataEntry {
+    pub index: u8,
+    pub value: MetadataValue
+}
+
+pub enum MetadataValue {
+    Byte {byte: i8},
+    Short {short: i16},
+    Int {int: i32},
+    Float {float: f32},
+    String {string: String},
+    Slot {slot: Slot},
+    Position {x: i32, y: i32, z: i32},
+    Rotation {pitch: f32, yaw: f32, roll: f32}
+}
+
+impl EntityMetadata {
+    pub fn new() -> EntityMetadata {
+        EntityMetadata {entries: Vec::new()}
+    }
+
+    pub fn add(mut self, index: u8, value: MetadataValue) -> EntityMetadata {
+        self.entries.push(MetadataEntry {index, value});
+        self
+    }
+
+    pub fn write(&self, writer: &mut DataWriter) {
+        for entry in &self.entries {
+            writer.write_u8((entry.value.type_id() << 5) | (entry.index & 0x1F));
+            match &entry.value {
+                MetadataValue::Byte {byte} => writer.write_i8(*byte),
+                MetadataValue::Short {short} => writer.write_i16(*short),
+                MetadataValue::Int {int} => writer.write_i32(*int),
+                MetadataValue::Float {float} => writer.write_f32(*float),
+                MetadataValue::String {string} => writer.write_string(string),
+                MetadataValue::Slot {slot} => writer.write_slot(slot),
+                MetadataValue::Position {x, y, z} => {
+                    writer.write_i32(*x);
+                    writer.write_i32(*y);
+                    writer.write_i32(*z);
+                }
+                MetadataValue::Rotation {pitch, yaw, roll} => {
+                    writer.write_f32(*pitch);
+                    writer.write_f32(*yaw);
+                    writer.write_f32(*roll);
+                }
+            }
+        }
+        writer.write_u8(0x7F);
+    }
+}
+
+impl MetadataValue {
+    pub const fn type_id(&self) -> u8 {
+        match self {
+            MetadataValue::Byte {..} => 0,
+            MetadataValue::Short {..} => 1,
+            MetadataValue::Int {..} => 2,
+            MetadataValue::Float {..} => 3,
+            MetadataValue::String {..} => 4,
+            MetadataValue::Slot {..} => 5,
+            MetadataValue::Position {..} => 6,
+            MetadataValue::Rotation {..} => 7
+        }
+    }
+}
+
+#[test]
+pub fn test_entity_metadata() {
+    use crate::data_reader::DataReader;
+
+    let metadata = EntityMetadata::new()
+        .add(0, MetadataValue::Byte {byte: 0x02})
+        .add(1, MetadataValue::Short {short: 300})
+        .add(6, MetadataValue::Float {float: 20.0})
+        .add(7, MetadataValue::Int {int: 0x00FF00})
+        .add(2, MetadataValue::String {string: "Amethyst".to_string()});
+    let mut writer = DataWriter::new();
+    metadata.write(&mut writer);
+
+    let mut reader = DataReader::new(&writer.data);
+    assert_eq!(reader.read_u8(), Ok(0x00));
+    assert_eq!(reader.read_i8(), Ok(0x02));
+    assert_eq!(reader.read_u8(), Ok(0x21));
+    assert_eq!(reader.read_u16(), Ok(300));
+    assert_eq!(reader.read_u8(), Ok(0x66));
+    assert_eq!(reader.read_f32(), Ok(20.0));
+    assert_eq!(reader.read_u8(), Ok(0x47));
+    assert_eq!(reader.read_i32(), Ok(0x00FF00));
+    assert_eq!(reader.read_u8(), Ok(0x82));
+    assert_eq!(reader.read_string(), Ok("Amethyst".to_string()));
+    assert_eq!(reader.read_u8(), Ok(0x7F));
+    assert_eq!(reader.cursor, writer.data.len());
+}
\ No newline at end of file
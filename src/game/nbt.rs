@@ -2,7 +2,12 @@ use std::iter::Map;
 use crate::data_writer::DataWriter;
 use std::collections::HashMap;
 use std::any::Any;
+use crate::data_reader::DataReader;
 
+//Same nesting limit as vanilla, so deeply nested lists and compounds can't overflow the stack
+const MAX_DEPTH: usize = 512;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum NBTTag {
     End,
     Byte {byte: i8},
@@ -19,6 +24,7 @@ pub enum NBTTag {
     LongArray {array: Vec<i64>}
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct CompoundElement<> {
     pub name: String,
     pub tag: NBTTag
@@ -43,10 +49,21 @@ impl NBTTag {
         }
     }
 
+    //Reads a named root tag, as written by write with a name, the End tag (a single 0) is read as None
+    pub fn read(reader: &mut DataReader) -> Result<Option<NBTTag>, &'static str> {
+        let type_id = reader.read_u8()?;
+        if type_id == 0 {
+            return Ok(None);
+        }
+        read_string(reader)?;
+        Ok(Some(read_payload(reader, type_id, 0)?))
+    }
+
+    //Without a name only the payload is written, as list elements have no type id or name
     pub fn write<'a>(&self, data: &'a mut Vec<u8>, name: Option<&String>) -> &'a mut Vec<u8> {
-        data.push(self.type_id());
         if name.is_some() {
             let name = name.unwrap();
+            data.push(self.type_id());
             data.extend_from_slice(&(name.len() as i16).to_be_bytes());
             data.extend_from_slice(name.as_bytes());
         }
@@ -58,7 +75,10 @@ impl NBTTag {
             NBTTag::Long {long} => data.extend_from_slice(&long.to_be_bytes()),
             NBTTag::Float {float} => data.extend_from_slice(&float.to_be_bytes()),
             NBTTag::Double {double} => data.extend_from_slice(&double.to_be_bytes()),
-            NBTTag::ByteArray {bytes} => data.extend(bytes),
+            NBTTag::ByteArray {bytes} => {
+                data.extend_from_slice(&(bytes.len() as i32).to_be_bytes());
+                data.extend(bytes);
+            }
             NBTTag::String {string} => {
                 data.extend_from_slice(&(string.len() as i16).to_be_bytes());
                 data.extend_from_slice(string.as_bytes());
@@ -77,13 +97,13 @@ impl NBTTag {
                 data.push(0);
             }
             NBTTag::IntArray {array} => {
-                data.extend_from_slice(&array.len().to_be_bytes());
+                data.extend_from_slice(&(array.len() as i32).to_be_bytes());
                 for x in array {
                     data.extend_from_slice(&x.to_be_bytes());
                 }
             }
             NBTTag::LongArray {array} => {
-                data.extend_from_slice(&array.len().to_be_bytes());
+                data.extend_from_slice(&(array.len() as i32).to_be_bytes());
                 for x in array {
                     data.extend_from_slice(&x.to_be_bytes());
                 }
@@ -92,4 +112,100 @@ impl NBTTag {
 
         data
     }
+}
+
+//NBT strings have an unsigned short length instead of a varint
+fn read_string(reader: &mut DataReader) -> Result<String, &'static str> {
+    let length = reader.read_u16()? as usize;
+    String::from_utf8(reader.read_data_fixed(length)?).map_err(|_e| "Invalid NBT string")
+}
+
+fn read_length(reader: &mut DataReader) -> Result<usize, &'static str> {
+    let length = reader.read_i32()?;
+    if length < 0 {
+        return Err("Negative NBT length");
+    }
+    Ok(length as usize)
+}
+
+fn read_payload(reader: &mut DataReader, type_id: u8, depth: usize) -> Result<NBTTag, &'static str> {
+    if depth > MAX_DEPTH {
+        return Err("NBT is too deep");
+    }
+
+    Ok(match type_id {
+        1 => NBTTag::Byte {byte: reader.read_i8()?},
+        2 => NBTTag::Short {short: reader.read_i16()?},
+        3 => NBTTag::Int {int: reader.read_i32()?},
+        4 => NBTTag::Long {long: reader.read_i64()?},
+        5 => NBTTag::Float {float: reader.read_f32()?},
+        6 => NBTTag::Double {double: reader.read_f64()?},
+        7 => {
+            let length = read_length(reader)?;
+            NBTTag::ByteArray {bytes: reader.read_data_fixed(length)?}
+        }
+        8 => NBTTag::String {string: read_string(reader)?},
+        9 => {
+            let element_type = reader.read_u8()?;
+            let length = read_length(reader)?;
+            //Not preallocated, the length isn't trusted until the elements are actually read
+            let mut list = Vec::new();
+            for _ in 0..length {
+                list.push(read_payload(reader, element_type, depth + 1)?);
+            }
+            NBTTag::List {list, type_id: element_type}
+        }
+        10 => {
+            let mut compound = Vec::new();
+            loop {
+                let element_type = reader.read_u8()?;
+                if element_type == 0 {
+                    break;
+                }
+                let name = read_string(reader)?;
+                compound.push(CompoundElement {name, tag: read_payload(reader, element_type, depth + 1)?});
+            }
+            NBTTag::Compound {compound}
+        }
+        11 => {
+            let length = read_length(reader)?;
+            let mut array = Vec::new();
+            for _ in 0..length {
+                array.push(reader.read_i32()?);
+            }
+            NBTTag::IntArray {array}
+        }
+        12 => {
+            let length = read_length(reader)?;
+            let mut array = Vec::new();
+            for _ in 0..length {
+                array.push(reader.read_i64()?);
+            }
+            NBTTag::LongArray {array}
+        }
+        _ => return Err("Unknown NBT tag type")
+    })
+}
+
+#[test]
+pub fn test_nbt_round_trip() {
+    let tag = NBTTag::Compound {compound: vec!(
+        CompoundElement {name: "display".to_owned(), tag: NBTTag::Compound {compound: vec!(
+            CompoundElement {name: "Name".to_owned(), tag: NBTTag::String {string: "Pyrocah".to_owned()}},
+            CompoundElement {name: "Lore".to_owned(), tag: NBTTag::List {list: vec!(NBTTag::String {string: "Shiny".to_owned()}), type_id: 8}}
+        )}},
+        CompoundElement {name: "HideFlags".to_owned(), tag: NBTTag::Int {int: 63}},
+        CompoundElement {name: "Bytes".to_owned(), tag: NBTTag::ByteArray {bytes: vec!(1, 2, 3)}},
+        CompoundElement {name: "Colors".to_owned(), tag: NBTTag::IntArray {array: vec!(0xFF0000, 0x00FF00)}}
+    )};
+
+    let mut data = Vec::new();
+    tag.write(&mut data, Some(&String::new()));
+    let mut reader = DataReader::new(&data);
+    assert_eq!(NBTTag::read(&mut reader).unwrap(), Some(tag));
+    assert_eq!(reader.cursor, data.len());
+
+    assert_eq!(NBTTag::read(&mut DataReader::new(&[0])).unwrap(), None);
+    //List claiming more elements than it has
+    assert!(NBTTag::read(&mut DataReader::new(&[9, 0, 0, 1, 0x7F, 0xFF, 0xFF, 0xFF])).is_err());
 }
\ No newline at end of file
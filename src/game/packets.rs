@@ -5,7 +5,10 @@ use json::JsonValue;
 use uuid::Uuid;
 use crate::game::position::Position;
 use crate::game::nbt::NBTTag;
+use crate::game::metadata::EntityMetadata;
 use crate::net::network_manager::ConnectionState;
+use crate::data_reader::{EOF, STRING_TOO_LONG, NEGATIVE_LENGTH, VARINT_TOO_BIG, VARLONG_TOO_BIG};
+use std::fmt;
 
 pub enum Packet {
     Handshake {
@@ -37,6 +40,7 @@ pub enum Packet {
         nickname: String
     },
     DisconnectLogin {reason: ChatComponent},
+    SetCompression {threshold: i32},
 
     //Play
     KeepAlive {id: i32},
@@ -56,7 +60,29 @@ pub enum Packet {
         players: Vec<PlayerInfoPlayer>
     },
     DisconnectPlay {reason: ChatComponent},
+    Player {on_ground: bool},
+    PlayerPosition {
+        x: f64,
+        y: f64,
+        z: f64,
+        on_ground: bool
+    },
+    PlayerLook {
+        yaw: f32,
+        pitch: f32,
+        on_ground: bool
+    },
     PlayerPositionAndLook {
+        x: f64,
+        y: f64,
+        z: f64,
+        yaw: f32,
+        pitch: f32,
+        on_ground: bool
+    },
+    //Each flag bit makes its field relative instead of absolute:
+    //0x01 = x, 0x02 = y, 0x04 = z, 0x08 = yaw, 0x10 = pitch
+    ClientPlayerPositionAndLook {
         x: f64,
         y: f64,
         z: f64,
@@ -67,29 +93,472 @@ pub enum Packet {
     WorldBorder {
         action: WorldBorderAction
     },
+    //A negative time of day stops the sun at its absolute value
     TimeUpdate {
         world_age: i64,
         time_of_day: i64
     },
+    //Window 0 is the player inventory: 0 crafting output, 1-4 crafting grid, 5-8 armor, 9-35 main inventory, 36-44 hotbar
     WindowItems {
         window_id: u8,
         slots: Vec<Slot>
     },
+    //Data is the already packed blocks, light and biomes of the sections in the bitmask
     ChunkData {
-        x: i32,
-        y: i32,
+        chunk_x: i32,
+        chunk_z: i32,
         ground_up_continuous: bool,
-        bitmask: u16,
-        //Tests only
+        primary_bitmask: u16,
         data: Vec<u8>
     },
-    ClientChatMessage { message: String },
-    ServerChatMessage {
+    ServerChatMessage { message: String },
+    //Position: 0 = chat box, 1 = system message, 2 = above hotbar
+    ChatMessage {
         component: ChatComponent,
-        pos: u8
+        position: u8
+    },
+    //Block state is the block id << 4 | metadata
+    BlockChange {
+        location: Position,
+        block_state: i32
+    },
+    MultiBlockChange {
+        chunk_x: i32,
+        chunk_z: i32,
+        records: Vec<BlockChangeRecord>
+    },
+    //Coordinates are fixed-point (value * 32) and yaw/pitch are angles (1/256 of a turn)
+    SpawnPlayer {
+        entity_id: i32,
+        uuid: Uuid,
+        x: i32,
+        y: i32,
+        z: i32,
+        yaw: u8,
+        pitch: u8,
+        current_item: i16,
+        metadata: EntityMetadata
+    },
+    //Coordinates are fixed-point (value * 32), angles are 1/256 of a turn and velocities are in 1/8000 of a block per tick
+    SpawnMob {
+        entity_id: i32,
+        type_id: u8,
+        x: i32,
+        y: i32,
+        z: i32,
+        yaw: u8,
+        pitch: u8,
+        head_pitch: u8,
+        velocity_x: i16,
+        velocity_y: i16,
+        velocity_z: i16,
+        metadata: EntityMetadata
+    },
+    DestroyEntities {entity_ids: Vec<i32>},
+    //Deltas are fixed-point (value * 32), position::relative_delta tells if a move fits (otherwise it
+    //needs an Entity Teleport) and DataWriter::write_relative_delta writes a delta clamped to the byte
+    EntityRelativeMove {
+        entity_id: i32,
+        dx: i8,
+        dy: i8,
+        dz: i8,
+        on_ground: bool
+    },
+    EntityLook {
+        entity_id: i32,
+        yaw: u8,
+        pitch: u8,
+        on_ground: bool
+    },
+    EntityLookAndRelativeMove {
+        entity_id: i32,
+        dx: i8,
+        dy: i8,
+        dz: i8,
+        yaw: u8,
+        pitch: u8,
+        on_ground: bool
+    },
+    //Coordinates are fixed-point (value * 32), used when the move is too big for a relative move
+    EntityTeleport {
+        entity_id: i32,
+        x: i32,
+        y: i32,
+        z: i32,
+        yaw: u8,
+        pitch: u8,
+        on_ground: bool
+    },
+    EntityHeadLook {
+        entity_id: i32,
+        head_yaw: u8
+    },
+    //Chat mode: 0 = enabled, 1 = commands only, 2 = hidden
+    //1.8 has no main hand field, it was added in 1.9
+    ClientSettings {
+        locale: String,
+        view_distance: i8,
+        chat_mode: i8,
+        chat_colors: bool,
+        displayed_skin_parts: u8
+    },
+    //Data is the rest of the packet, it has no length prefix
+    PluginMessageOut {
+        channel: String,
+        data: Vec<u8>
+    },
+    PluginMessageIn {
+        channel: String,
+        data: Vec<u8>
+    },
+    //Health 0 makes the client show the death screen, it answers with a Client Status respawn
+    UpdateHealth {
+        health: f32,
+        food: i32,
+        food_saturation: f32
+    },
+    //Action: 0 = perform respawn, 1 = request stats, 2 = taking inventory achievement
+    ClientStatus {action_id: i32},
+    //Unlike JoinGame the dimension is a full int here: -1 = nether, 0 = overworld, 1 = end
+    Respawn {
+        dimension: i32,
+        difficulty: u8,
+        gamemode: u8,
+        level_type: String
+    },
+    //Reasons: 0 = invalid bed, 1 = end raining, 2 = begin raining, 3 = change gamemode (value is the gamemode),
+    //4 = enter credits, 5 = demo message, 6 = arrow hitting player, 7 = fade value (rain level),
+    //8 = fade time (thunder level), 10 = elder guardian appearance
+    ChangeGameState {
+        reason: u8,
+        value: f32
+    },
+    //Flags: 0x01 = invulnerable, 0x02 = flying, 0x04 = allow flying, 0x08 = creative mode
+    PlayerAbilities {
+        flags: u8,
+        flying_speed: f32,
+        field_of_view: f32
+    },
+    //Sent when the player starts or stops flying, same flags as PlayerAbilities
+    ServerPlayerAbilities {
+        flags: u8,
+        flying_speed: f32,
+        walking_speed: f32
+    },
+    //Hotbar slot selected by the player, 0 to 8
+    HeldItemChangeServer {slot: i16},
+    //Player swung its arm, it has no fields in 1.8
+    AnimationServer,
+    //Animation: 0 = swing arm, 1 = take damage, 2 = leave bed, 3 = eat food, 4 = critical effect, 5 = magic critical effect
+    Animation {
+        entity_id: i32,
+        animation: u8
+    },
+    //Status: 0 = started digging, 1 = cancelled digging, 2 = finished digging,
+    //3 = drop item stack, 4 = drop item, 5 = shoot arrow / finish eating
+    //Face: 0 = -Y, 1 = +Y, 2 = -Z, 3 = +Z, 4 = -X, 5 = +X
+    PlayerDigging {
+        status: i8,
+        location: Position,
+        face: i8
+    },
+    //Cursor is the position inside the block face, 0 to 15
+    //Location -1, -1, -1 with face -1 means using the held item instead of placing it on a block
+    BlockPlacement {
+        location: Position,
+        face: i8,
+        held_item: Slot,
+        cursor_x: i8,
+        cursor_y: i8,
+        cursor_z: i8
+    },
+    //Window -1 with slot -1 sets the item held by the cursor
+    SetSlot {
+        window_id: i8,
+        slot: i16,
+        item: Slot
+    },
+    //Window type like minecraft:chest, the entity id is only sent for horse windows (EntityHorse)
+    OpenWindow {
+        window_id: u8,
+        window_type: String,
+        title: ChatComponent,
+        slot_count: u8,
+        entity_id: Option<i32>
+    },
+    //Same packet in both directions
+    CloseWindow {window_id: u8},
+    //Same packet in both directions, a rejected transaction has to be sent back by the client to be applied again
+    ConfirmTransaction {
+        window_id: i8,
+        action_number: i16,
+        accepted: bool
+    },
+    //Mode and button combinations:
+    //0 = click: button 0 left, 1 right
+    //1 = shift click: button 0 left, 1 right
+    //2 = number key: button is the hotbar slot, 0 to 8
+    //3 = middle click (creative only): button 2
+    //4 = drop: button 0 drops one (Q), 1 drops the stack (Ctrl+Q), slot -999 with no item is a click outside
+    //5 = drag: button 0/4 starts left/right drag, 1/5 adds a slot, 2/6 ends it, start and end have slot -999
+    //6 = double click: button 0
+    //The clicked item is what the client thinks is in the slot, the server answers with a ConfirmTransaction
+    ClickWindow {
+        window_id: u8,
+        slot: i16,
+        button: i8,
+        action_number: i16,
+        mode: i8,
+        clicked_item: Slot
+    },
+    //Velocity in 1/8000 of a block per tick, see position::velocity
+    EntityVelocity {
+        entity_id: i32,
+        vx: i16,
+        vy: i16,
+        vz: i16
+    },
+    //Slot: 0 = held item, 1 = boots, 2 = leggings, 3 = chestplate, 4 = helmet
+    EntityEquipment {
+        entity_id: i32,
+        slot: i16,
+        item: Slot
+    },
+    //Only the changed entries need to be sent
+    EntityMetadataPacket {
+        entity_id: i32,
+        metadata: EntityMetadata
+    },
+    //Common statuses: 2 = hurt, 3 = dead, 6 = taming failed (smoke), 7 = taming succeeded (hearts),
+    //9 = finished eating, 10 = sheep eating grass, 18 = animal in love (hearts)
+    EntityStatus {
+        entity_id: i32,
+        status: i8
+    },
+    //Pickup animation, the collected item entity is destroyed right after
+    CollectItem {
+        collected_entity_id: i32,
+        collector_entity_id: i32
+    },
+    //Coordinates are fixed-point (value * 32), the meaning of data depends on the object type
+    //and the velocity is only sent when data isn't 0
+    SpawnObject {
+        entity_id: i32,
+        type_id: i8,
+        x: i32,
+        y: i32,
+        z: i32,
+        pitch: u8,
+        yaw: u8,
+        data: i32,
+        velocity: Option<(i16, i16, i16)>
+    },
+    //Coordinates are fixed-point (value * 32)
+    SpawnExperienceOrb {
+        entity_id: i32,
+        x: i32,
+        y: i32,
+        z: i32,
+        count: i16
+    },
+    //Title is the painting name (e.g. Kebab), location is its center block
+    //Direction: 0 = south, 1 = west, 2 = north, 3 = east
+    SpawnPainting {
+        entity_id: i32,
+        title: String,
+        location: Position,
+        direction: u8
+    },
+    //Coordinates are fixed-point (value * 8), pitch is value * 63 so 63 is the normal pitch
+    //Volume 1 is full volume, bigger values can be heard from further away
+    NamedSoundEffect {
+        sound: String,
+        x: i32,
+        y: i32,
+        z: i32,
+        volume: f32,
+        pitch: u8
+    },
+    //Offsets are multiplied by random numbers to spread the particles, data is the speed for most particles
+    //Extra is sent as varints: iconcrack (36) has the item id and damage,
+    //blockcrack (37) and blockdust (38) have the block id | (metadata << 12)
+    Particle {
+        particle_id: i32,
+        long_distance: bool,
+        x: f32,
+        y: f32,
+        z: f32,
+        offset_x: f32,
+        offset_y: f32,
+        offset_z: f32,
+        data: f32,
+        count: i32,
+        extra: Vec<i32>
+    },
+    //Effects: 1000 = dispenser dispenses, 1003 = door open/close, 1005 = play record (data is the record item id),
+    //2000 = dispenser smoke (data is the direction), 2001 = block break (data is the block state), 2004 = mob spawner particles
+    //Disabling the relative volume plays it at the same volume everywhere, only used by 1013 (wither spawn) and 1018 (dragon death)
+    Effect {
+        effect_id: i32,
+        location: Position,
+        data: i32,
+        disable_relative_volume: bool
+    },
+    //Stage 0 to 9, any other value removes the animation
+    //The entity id only identifies the animation, so each breaking player needs its own
+    BlockBreakAnimation {
+        entity_id: i32,
+        location: Position,
+        stage: i8
+    },
+    //Action id and param depend on the block type (the block id, without metadata):
+    //note block (25) instrument and pitch (0 to 24), piston (29, 33) extend/retract and direction,
+    //chest (54, 130, 146) 1 and the number of players with it open
+    BlockAction {
+        location: Position,
+        action_id: u8,
+        action_param: u8,
+        block_type: i32
+    },
+    //Records are the destroyed blocks relative to the center, the player velocity is added to the receiving player's
+    Explosion {
+        x: f32,
+        y: f32,
+        z: f32,
+        radius: f32,
+        records: Vec<(i8, i8, i8)>,
+        player_vx: f32,
+        player_vy: f32,
+        player_vz: f32
+    },
+    //Text is everything before the cursor, the looked at block is only sent when has_position is true
+    TabComplete {
+        text: String,
+        has_position: bool,
+        looked_at_block: Option<Position>
+    },
+    TabCompleteResponse {matches: Vec<String>},
+    //Both are always sent, an empty component clears that part
+    PlayerListHeaderFooter {
+        header: ChatComponent,
+        footer: ChatComponent
+    },
+    //Mode: 0 = create, 1 = remove, 2 = update the display text
+    //Value (the display text) and type (integer or hearts) are only sent when creating or updating
+    ScoreboardObjective {
+        name: String,
+        mode: i8,
+        value: String,
+        type_: String
+    },
+    //Action: 0 = create or update, 1 = remove (the value isn't sent)
+    UpdateScore {
+        name: String,
+        action: i8,
+        objective: String,
+        value: i32
+    },
+    //Position: 0 = list, 1 = sidebar, 2 = below name, 3 to 18 = team specific sidebar by color
+    DisplayScoreboard {
+        position: i8,
+        name: String
+    },
+    //Mode: 0 = create, 1 = remove, 2 = update info, 3 = add players, 4 = remove players, the data has to match it
+    Teams {
+        name: String,
+        mode: i8,
+        data: TeamData
+    },
+    Title {action: TitleAction},
+    UseEntity {
+        target: i32,
+        action: UseEntityAction
+    },
+    //Action: 0 = start sneaking, 1 = stop sneaking, 2 = leave bed, 3 = start sprinting, 4 = stop sprinting,
+    //5 = jump with horse, 6 = open ridden horse inventory
+    //Jump boost is only used by the horse jump, from 0 to 100
+    EntityAction {
+        entity_id: i32,
+        action_id: i32,
+        jump_boost: i32
+    },
+    //Spectators teleport to the entity with this UUID
+    Spectate {target: Uuid},
+    //Same packet in both directions, each line is a chat component
+    UpdateSign {
+        location: Position,
+        lines: [ChatComponent; 4]
+    },
+    //The sign has to be already placed at the location, the client answers with UpdateSign
+    OpenSignEditor {location: Position},
+    //Hash is the SHA-1 of the pack as 40 lowercase hex characters, used by the client to cache it
+    ResourcePackSend {
+        url: String,
+        hash: String
+    },
+    //Result: 0 = successfully loaded, 1 = declined, 2 = failed download, 3 = accepted
+    ResourcePackStatus {
+        hash: String,
+        result: i32
+    },
+    //Only the map metadata and icons are sent when columns is 0, otherwise the colors of the updated rectangle
+    Map {
+        map_id: i32,
+        scale: i8,
+        icons: Vec<MapIcon>,
+        columns: u8,
+        rows: u8,
+        x: u8,
+        z: u8,
+        data: Vec<u8>
+    },
+    CombatEvent {event: CombatEventKind},
+    //Sending the player's own entity id restores the normal view
+    Camera {camera_id: i32},
+    //Sent to the other players, the location is the bed's head block
+    UseBed {
+        entity_id: i32,
+        location: Position
+    },
+    //Answer to a Client Status stats request, names like stat.jump or achievement.openInventory
+    Statistics {entries: Vec<(String, i32)>},
+    //Data is the payloads of every chunk, in the same order as the chunk metas
+    MapChunkBulk {
+        sky_light_sent: bool,
+        chunks: Vec<ChunkMeta>,
+        data: Vec<u8>
+    }
+}
+
+//Vanilla accepts up to 384 characters of JSON per line
+fn read_sign_line(reader: &mut DataReader) -> Result<ChatComponent, PacketError> {
+    let line = reader.read_string_limited(384 * 4)?;
+    if line.chars().count() > 384 {
+        return Err(PacketError::StringTooLong);
+    }
+    let json = json::parse(&line).map_err(|_e| PacketError::Invalid("Invalid sign line json"))?;
+    Ok(ChatComponent::from_json(&json)?)
+}
+
+//Malicious clients may send NaN or infinite values to break the server's physics
+fn read_coordinate(reader: &mut DataReader) -> Result<f64, &'static str> {
+    let value = reader.read_f64()?;
+    if !value.is_finite() {
+        return Err("Invalid coordinate");
     }
+    Ok(value)
 }
 
+fn read_rotation(reader: &mut DataReader) -> Result<f32, &'static str> {
+    let value = reader.read_f32()?;
+    if !value.is_finite() {
+        return Err("Invalid rotation");
+    }
+    Ok(value)
+}
+
+//Item stack, empty slots have the item id -1 and no other field
+#[derive(Debug, Clone, PartialEq)]
 pub struct Slot {
     pub item_id: i16,
     pub item_count: Option<i8>,
@@ -97,6 +566,14 @@ pub struct Slot {
     pub nbt: Option<NBTTag>
 }
 
+impl Slot {
+    pub fn empty() -> Slot {
+        Slot {item_id: -1, item_count: None, item_damage: None, nbt: None}
+    }
+}
+
+//Despite the names, the radius fields are the border's diameter in blocks
+//Speeds are the milliseconds until the new radius is reached, sent as a VarLong
 pub enum WorldBorderAction {
     SetSize {
         radius: f64
@@ -104,7 +581,7 @@ pub enum WorldBorderAction {
     LerpSize {
         old_radius: f64,
         new_radius: f64,
-        speed: i32
+        speed: i64
     },
     SetCenter {
         x: f64,
@@ -149,60 +626,344 @@ pub enum PlayerInfoAction {
     RemovePlayer
 }
 
+#[derive(Clone)]
 pub struct PlayerInfoProperties {
     pub name: String,
     pub value: String,
     pub signature: Option<String>
 }
 
+pub struct BlockChangeRecord {
+    //Chunk relative x << 4 | z
+    pub horizontal_position: u8,
+    pub y: u8,
+    pub block_state: i32
+}
+
+impl BlockChangeRecord {
+    //x and z are relative to the chunk (0-15)
+    pub const fn new(x: u8, y: u8, z: u8, block_state: i32) -> BlockChangeRecord {
+        BlockChangeRecord {horizontal_position: ((x & 0x0F) << 4) | (z & 0x0F), y, block_state}
+    }
+}
+
+//Window id and slot of SetSlot that change the item held by the cursor
+pub const CURSOR_WINDOW: i8 = -1;
+pub const CURSOR_SLOT: i16 = -1;
+
+//Friendly fire: 0x01 = allowed, 0x02 = can see invisible teammates
+//Name tag visibility: always, hideForOtherTeams, hideForOwnTeam or never
+//Color is a chat color index (0 to 15) or -1 for none
+pub enum TeamData {
+    Create {
+        display_name: String,
+        prefix: String,
+        suffix: String,
+        friendly_fire: i8,
+        name_tag_visibility: String,
+        color: i8,
+        players: Vec<String>
+    },
+    Remove,
+    UpdateInfo {
+        display_name: String,
+        prefix: String,
+        suffix: String,
+        friendly_fire: i8,
+        name_tag_visibility: String,
+        color: i8
+    },
+    AddPlayers {players: Vec<String>},
+    RemovePlayers {players: Vec<String>}
+}
+
+//Team members are a varint count followed by each player name
+fn write_team_players(writer: &mut DataWriter, players: &Vec<String>) {
+    writer.write_varint(players.len() as i32);
+    for player in players {
+        writer.write_string(player);
+    }
+}
+
+//Times are in ticks, hide removes the title until the next one and reset also restores the default times
+pub enum TitleAction {
+    SetTitle(ChatComponent),
+    SetSubtitle(ChatComponent),
+    SetTimes {
+        fade_in: i32,
+        stay: i32,
+        fade_out: i32
+    },
+    Hide,
+    Reset
+}
+
+//Interact at carries where the entity was hit, relative to it (used by armor stands)
+pub enum UseEntityAction {
+    Interact,
+    Attack,
+    InteractAt {
+        x: f32,
+        y: f32,
+        z: f32
+    }
+}
+
+//Direction is 0 to 15 (1/16 of a turn), type 0 is the white player pointer
+//Coordinates are from -128 to 127, with 0, 0 as the center of the map
+pub struct MapIcon {
+    pub direction: u8,
+    pub icon_type: u8,
+    pub x: i8,
+    pub z: i8
+}
+
+//End combat duration is in ticks, entity ids are the killer or last attacker (-1 if none)
+//Entity dead shows the death screen with the message to the dead player
+pub enum CombatEventKind {
+    EnterCombat,
+    EndCombat {
+        duration: i32,
+        entity_id: i32
+    },
+    EntityDead {
+        player_id: i32,
+        entity_id: i32,
+        message: ChatComponent
+    }
+}
+
+//Header of each chunk column sent in a Map Chunk Bulk
+pub struct ChunkMeta {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    pub primary_bitmask: u16
+}
+
+//Why a packet couldn't be read or written
+#[derive(Debug, Clone, PartialEq)]
+pub enum PacketError {
+    UnknownId {state: ConnectionState, id: i32},
+    UnexpectedEof,
+    StringTooLong,
+    NegativeLength,
+    VarIntTooBig,
+    VarLongTooBig,
+    //Packet that doesn't belong to this connection state or direction, like serializing a serverbound packet
+    InvalidState,
+    //The zlib encoder failed, the io error is logged where it happens
+    Compression,
+    //Any other invalid field, like an unknown enum value or a NaN coordinate
+    Invalid(&'static str)
+}
+
+impl PacketError {
+    //Short reason to be shown to the client when it's disconnected
+    pub fn reason(&self) -> &'static str {
+        match self {
+            PacketError::UnknownId {..} => "Unknown packet id",
+            PacketError::UnexpectedEof => EOF,
+            PacketError::StringTooLong => STRING_TOO_LONG,
+            PacketError::NegativeLength => NEGATIVE_LENGTH,
+            PacketError::VarIntTooBig => VARINT_TOO_BIG,
+            PacketError::VarLongTooBig => VARLONG_TOO_BIG,
+            PacketError::InvalidState => "Packet not valid in this connection state",
+            PacketError::Compression => "Couldn't compress the packet",
+            PacketError::Invalid(reason) => reason
+        }
+    }
+}
+
+//The data reader errors are plain strings, every one of its consts has its own variant
+impl From<&'static str> for PacketError {
+    fn from(error: &'static str) -> Self {
+        match error {
+            EOF => PacketError::UnexpectedEof,
+            STRING_TOO_LONG => PacketError::StringTooLong,
+            NEGATIVE_LENGTH => PacketError::NegativeLength,
+            VARINT_TOO_BIG => PacketError::VarIntTooBig,
+            VARLONG_TOO_BIG => PacketError::VarLongTooBig,
+            _ => PacketError::Invalid(error)
+        }
+    }
+}
+
+impl fmt::Display for PacketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PacketError::UnknownId {state, id} => write!(f, "Unknown packet id 0x{:02X} in {:?} state", id, state),
+            _ => write!(f, "{}", self.reason())
+        }
+    }
+}
+
 impl Packet {
-    pub fn read<'a>(id: i32, reader: &mut DataReader, state: ConnectionState) -> Option<Packet> {
+    pub fn read<'a>(id: i32, reader: &mut DataReader, state: ConnectionState) -> Result<Packet, PacketError> {
         match state {
             ConnectionState::Play => {
                 match id {
-                    0x00 => Some(Packet::KeepAlive {id: reader.read_varint()?}),
-                    0x01 => Some(Packet::ClientChatMessage {message: reader.read_string()?}),
-                    _ => None
+                    0x00 => Ok(Packet::KeepAlive {id: reader.read_varint()?}),
+                    0x01 => {
+                        //Up to 4 bytes per character in UTF-8
+                        let message = reader.read_string_limited(100 * 4)?;
+                        //Vanilla client doesn't send messages longer than 100 characters
+                        if message.chars().count() > 100 {
+                            return Err(PacketError::StringTooLong);
+                        }
+                        Ok(Packet::ServerChatMessage {message})
+                    }
+                    0x02 => {
+                        let target = reader.read_varint()?;
+                        let action = match reader.read_varint()? {
+                            0 => UseEntityAction::Interact,
+                            1 => UseEntityAction::Attack,
+                            2 => UseEntityAction::InteractAt {
+                                x: reader.read_f32()?,
+                                y: reader.read_f32()?,
+                                z: reader.read_f32()?
+                            },
+                            _ => return Err(PacketError::Invalid("Unknown use entity action"))
+                        };
+                        Ok(Packet::UseEntity {target, action})
+                    }
+                    0x03 => Ok(Packet::Player {on_ground: reader.read_bool()?}),
+                    0x04 => Ok(Packet::PlayerPosition {
+                        x: read_coordinate(reader)?,
+                        y: read_coordinate(reader)?,
+                        z: read_coordinate(reader)?,
+                        on_ground: reader.read_bool()?
+                    }),
+                    0x05 => Ok(Packet::PlayerLook {
+                        yaw: read_rotation(reader)?,
+                        pitch: read_rotation(reader)?,
+                        on_ground: reader.read_bool()?
+                    }),
+                    0x06 => Ok(Packet::PlayerPositionAndLook {
+                        x: read_coordinate(reader)?,
+                        y: read_coordinate(reader)?,
+                        z: read_coordinate(reader)?,
+                        yaw: read_rotation(reader)?,
+                        pitch: read_rotation(reader)?,
+                        on_ground: reader.read_bool()?
+                    }),
+                    0x07 => Ok(Packet::PlayerDigging {
+                        status: reader.read_i8()?,
+                        location: reader.read_position()?,
+                        face: reader.read_i8()?
+                    }),
+                    0x08 => Ok(Packet::BlockPlacement {
+                        location: reader.read_position()?,
+                        face: reader.read_i8()?,
+                        held_item: reader.read_slot()?,
+                        cursor_x: reader.read_i8()?,
+                        cursor_y: reader.read_i8()?,
+                        cursor_z: reader.read_i8()?
+                    }),
+                    0x09 => {
+                        let slot = reader.read_i16()?;
+                        if slot < 0 || slot > 8 {
+                            return Err(PacketError::Invalid("Invalid hotbar slot"));
+                        }
+                        Ok(Packet::HeldItemChangeServer {slot})
+                    }
+                    0x0A => Ok(Packet::AnimationServer),
+                    0x0B => Ok(Packet::EntityAction {
+                        entity_id: reader.read_varint()?,
+                        action_id: reader.read_varint()?,
+                        jump_boost: reader.read_varint()?
+                    }),
+                    0x0D => Ok(Packet::CloseWindow {window_id: reader.read_u8()?}),
+                    0x0E => Ok(Packet::ClickWindow {
+                        window_id: reader.read_u8()?,
+                        slot: reader.read_i16()?,
+                        button: reader.read_i8()?,
+                        action_number: reader.read_i16()?,
+                        mode: reader.read_i8()?,
+                        clicked_item: reader.read_slot()?
+                    }),
+                    0x0F => Ok(Packet::ConfirmTransaction {
+                        window_id: reader.read_i8()?,
+                        action_number: reader.read_i16()?,
+                        accepted: reader.read_bool()?
+                    }),
+                    0x12 => {
+                        let location = reader.read_position()?;
+                        let mut lines = [ChatComponent::new(), ChatComponent::new(), ChatComponent::new(), ChatComponent::new()];
+                        for line in lines.iter_mut() {
+                            *line = read_sign_line(reader)?;
+                        }
+                        Ok(Packet::UpdateSign {location, lines})
+                    }
+                    0x13 => Ok(Packet::ServerPlayerAbilities {
+                        flags: reader.read_u8()?,
+                        flying_speed: reader.read_f32()?,
+                        walking_speed: reader.read_f32()?
+                    }),
+                    0x14 => {
+                        let text = reader.read_string()?;
+                        let has_position = reader.read_bool()?;
+                        let looked_at_block = if has_position {Some(reader.read_position()?)} else {None};
+                        Ok(Packet::TabComplete {text, has_position, looked_at_block})
+                    }
+                    0x15 => Ok(Packet::ClientSettings {
+                        locale: reader.read_string_limited(16)?,
+                        view_distance: reader.read_i8()?,
+                        chat_mode: reader.read_i8()?,
+                        chat_colors: reader.read_bool()?,
+                        displayed_skin_parts: reader.read_u8()?
+                    }),
+                    0x16 => Ok(Packet::ClientStatus {action_id: reader.read_varint()?}),
+                    0x17 => {
+                        let channel = reader.read_string_limited(20)?;
+                        Ok(Packet::PluginMessageIn {channel, data: reader.read_remaining()})
+                    }
+                    0x18 => Ok(Packet::Spectate {target: reader.read_uuid()?}),
+                    0x19 => Ok(Packet::ResourcePackStatus {
+                        hash: reader.read_string_limited(40)?,
+                        result: reader.read_varint()?
+                    }),
+                    _ => Err(PacketError::UnknownId {state, id})
                 }
             }
             ConnectionState::Login => {
                 match id {
-                    0x00 => Some(Packet::LoginStart { nickname: reader.read_string()? }),
+                    //Names are checked by the login handler, this limit only stops huge strings
+                    0x00 => Ok(Packet::LoginStart { nickname: reader.read_string_limited(64)? }),
                     0x01 => {
                         let shared_secret_length = reader.read_varint()?;
                         let shared_secret = reader.read_data_fixed(shared_secret_length as usize)?;
                         let verify_token_length = reader.read_varint()?;
                         let verify_token = reader.read_data_fixed(verify_token_length as usize)?;
 
-                        Some(Packet::EncryptionResponse {
+                        Ok(Packet::EncryptionResponse {
                             shared_secret,
                             verify_token
                         })
                     }
-                    _ => None
+                    _ => Err(PacketError::UnknownId {state, id})
                 }
             }
             ConnectionState::Handshaking => {
                 match id {
-                    0x00 => Some(Packet::Handshake {
+                    0x00 => Ok(Packet::Handshake {
                             protocol_version: reader.read_varint()?,
-                            server_address: reader.read_string()?,
+                            server_address: reader.read_string_limited(255)?,
                             server_port: reader.read_u16()?,
                             next_state: reader.read_u8()?, }),
-                    _ => None
+                    _ => Err(PacketError::UnknownId {state, id})
                 }
             }
             ConnectionState::Status => {
                 match id {
-                    0x00 => Some(Packet::StatusRequest),
-                    0x01 => Some(Packet::Ping { ping: reader.read_i64()? }),
-                    _ => None
+                    0x00 => Ok(Packet::StatusRequest),
+                    0x01 => Ok(Packet::Ping { ping: reader.read_i64()? }),
+                    _ => Err(PacketError::UnknownId {state, id})
                 }
             }
         }
     }
 
-    pub fn serialize<'a>(&self) -> Option<Vec<u8>> {
+    pub fn serialize<'a>(&self) -> Result<Vec<u8>, PacketError> {
         let mut writer = DataWriter::new();
         match self {
             Packet::EncryptionRequest {
@@ -219,11 +980,11 @@ impl Packet {
             }
             Packet::DisconnectLogin {reason} => {
                 writer.write_u8(0x00);
-                writer.write_string(&reason.to_string());
+                writer.write_string(&reason.to_json_string());
             }
             Packet::DisconnectPlay {reason} => {
                 writer.write_u8(0x40);
-                writer.write_string(&reason.to_string());
+                writer.write_string(&reason.to_json_string());
             }
             Packet::StatusResponse {json} => {
                 writer.write_u8(0x00);
@@ -238,6 +999,10 @@ impl Packet {
                 writer.write_string(&uuid.to_hyphenated().to_string());
                 writer.write_string(nickname)
             }
+            Packet::SetCompression {threshold} => {
+                writer.write_u8(0x03);
+                writer.write_varint(*threshold);
+            }
             Packet::KeepAlive {id} => {
                 writer.write_u8(0x00);
                 writer.write_varint(*id);
@@ -273,7 +1038,7 @@ impl Packet {
                 writer.write_varint(*action_id);
                 writer.write_varint(players.len() as i32);
                 for player in players {
-                    writer.write_vec_data(&player.uuid.as_bytes().to_vec());
+                    writer.write_uuid(&player.uuid);
                     match &player.action {
                         PlayerInfoAction::AddPlayer {
                             name,
@@ -298,7 +1063,7 @@ impl Packet {
                             writer.write_varint(*ping);
                             if display_name.is_some() {
                                 writer.write_bool(true);
-                                writer.write_string(&display_name.as_ref().unwrap().to_string());
+                                writer.write_string(&display_name.as_ref().unwrap().to_json_string());
                             } else {
                                 writer.write_bool(false);
                             }
@@ -312,7 +1077,7 @@ impl Packet {
                         PlayerInfoAction::UpdateDisplayName {display_name} => {
                             if display_name.is_some() {
                                 writer.write_bool(true);
-                                writer.write_string(&display_name.as_ref().unwrap().to_string());
+                                writer.write_string(&display_name.as_ref().unwrap().to_json_string());
                             } else {
                                 writer.write_bool(false);
                             }
@@ -321,7 +1086,7 @@ impl Packet {
                     };
                 }
             }
-            Packet::PlayerPositionAndLook {
+            Packet::ClientPlayerPositionAndLook {
                 x,
                 y,
                 z,
@@ -348,7 +1113,7 @@ impl Packet {
                         writer.write_varint(1);
                         writer.write_f64(*old_radius);
                         writer.write_f64(*new_radius);
-                        writer.write_varint(*speed);
+                        writer.write_varlong(*speed);
                     }
                     WorldBorderAction::SetCenter {x, z} => {
                         writer.write_varint(2);
@@ -386,56 +1151,1532 @@ impl Packet {
                 }
             }
             Packet::TimeUpdate {world_age, time_of_day} => {
-                writer.write_varint(0x03);
+                writer.write_u8(0x03);
                 writer.write_i64(*world_age);
-                writer.write_i64(*time_of_day)
+                writer.write_i64(*time_of_day);
             }
             Packet::WindowItems {window_id, slots} => {
-                writer.write_varint(0x30);
+                writer.write_u8(0x30);
                 writer.write_u8(*window_id);
                 writer.write_i16(slots.len() as i16);
                 for slot in slots {
-                    writer.write_i16(slot.item_id);
-                    if slot.item_id > 0 {
-                        writer.write_i8(slot.item_count.unwrap());
-                        writer.write_i16(slot.item_damage.unwrap());
-                        if slot.nbt.is_none() {
-                            writer.write_u8(0);
-                        } else {
-                            slot.nbt.as_ref().unwrap().write(&mut writer.data, Some(&String::new()));
-                        }
-                    }
+                    writer.write_slot(slot);
                 }
             }
             Packet::ChunkData {
-                x,
-                y,
+                chunk_x,
+                chunk_z,
                 ground_up_continuous,
-                bitmask,
+                primary_bitmask,
                 data
             } => {
                 writer.write_u8(0x21);
-                writer.write_i32(*x);
-                writer.write_i32(*y);
+                writer.write_i32(*chunk_x);
+                writer.write_i32(*chunk_z);
                 writer.write_bool(*ground_up_continuous);
-                writer.write_u16(*bitmask);
+                //1.8 sends the bitmask as an unsigned short, not a varint
+                writer.write_u16(*primary_bitmask);
                 writer.write_varint((data.len() as i32));
                 writer.write_vec_data(data);
             }
-            Packet::ServerChatMessage {component, pos} => {
+            Packet::ChatMessage {component, position} => {
                 writer.write_u8(0x02);
-                writer.write_string(&component.to_string());
-                writer.write_u8(*pos);
+                writer.write_string(&component.to_json_string());
+                writer.write_u8(*position);
             }
-            _ => return None
-        }
-
-        Some(writer.data)
-    }
-
-    pub fn serialize_length(&self) -> Option<Vec<u8>> {
-        let mut data = self.serialize()?;
-        data.splice(0..0, DataWriter::get_varint(data.len() as u32));
-        Some(data)
+            Packet::BlockChange {location, block_state} => {
+                writer.write_u8(0x23);
+                writer.write_position(location);
+                writer.write_varint(*block_state);
+            }
+            Packet::MultiBlockChange {chunk_x, chunk_z, records} => {
+                writer.write_u8(0x22);
+                writer.write_i32(*chunk_x);
+                writer.write_i32(*chunk_z);
+                writer.write_varint(records.len() as i32);
+                for record in records {
+                    writer.write_u8(record.horizontal_position);
+                    writer.write_u8(record.y);
+                    writer.write_varint(record.block_state);
+                }
+            }
+            Packet::SpawnPlayer {
+                entity_id,
+                uuid,
+                x,
+                y,
+                z,
+                yaw,
+                pitch,
+                current_item,
+                metadata
+            } => {
+                writer.write_u8(0x0C);
+                writer.write_varint(*entity_id);
+                writer.write_uuid(uuid);
+                writer.write_i32(*x);
+                writer.write_i32(*y);
+                writer.write_i32(*z);
+                writer.write_u8(*yaw);
+                writer.write_u8(*pitch);
+                writer.write_i16(*current_item);
+                metadata.write(&mut writer);
+            }
+            Packet::SpawnMob {
+                entity_id,
+                type_id,
+                x,
+                y,
+                z,
+                yaw,
+                pitch,
+                head_pitch,
+                velocity_x,
+                velocity_y,
+                velocity_z,
+                metadata
+            } => {
+                writer.write_u8(0x0F);
+                writer.write_varint(*entity_id);
+                writer.write_u8(*type_id);
+                writer.write_i32(*x);
+                writer.write_i32(*y);
+                writer.write_i32(*z);
+                writer.write_u8(*yaw);
+                writer.write_u8(*pitch);
+                writer.write_u8(*head_pitch);
+                writer.write_i16(*velocity_x);
+                writer.write_i16(*velocity_y);
+                writer.write_i16(*velocity_z);
+                metadata.write(&mut writer);
+            }
+            Packet::DestroyEntities {entity_ids} => {
+                writer.write_u8(0x13);
+                writer.write_varint(entity_ids.len() as i32);
+                for entity_id in entity_ids {
+                    writer.write_varint(*entity_id);
+                }
+            }
+            Packet::EntityRelativeMove {entity_id, dx, dy, dz, on_ground} => {
+                writer.write_u8(0x15);
+                writer.write_varint(*entity_id);
+                writer.write_i8(*dx);
+                writer.write_i8(*dy);
+                writer.write_i8(*dz);
+                writer.write_bool(*on_ground);
+            }
+            Packet::EntityLook {entity_id, yaw, pitch, on_ground} => {
+                writer.write_u8(0x16);
+                writer.write_varint(*entity_id);
+                writer.write_u8(*yaw);
+                writer.write_u8(*pitch);
+                writer.write_bool(*on_ground);
+            }
+            Packet::EntityLookAndRelativeMove {entity_id, dx, dy, dz, yaw, pitch, on_ground} => {
+                writer.write_u8(0x17);
+                writer.write_varint(*entity_id);
+                writer.write_i8(*dx);
+                writer.write_i8(*dy);
+                writer.write_i8(*dz);
+                writer.write_u8(*yaw);
+                writer.write_u8(*pitch);
+                writer.write_bool(*on_ground);
+            }
+            Packet::EntityTeleport {entity_id, x, y, z, yaw, pitch, on_ground} => {
+                writer.write_u8(0x18);
+                writer.write_varint(*entity_id);
+                writer.write_i32(*x);
+                writer.write_i32(*y);
+                writer.write_i32(*z);
+                writer.write_u8(*yaw);
+                writer.write_u8(*pitch);
+                writer.write_bool(*on_ground);
+            }
+            Packet::EntityHeadLook {entity_id, head_yaw} => {
+                writer.write_u8(0x19);
+                writer.write_varint(*entity_id);
+                writer.write_u8(*head_yaw);
+            }
+            Packet::PluginMessageOut {channel, data} => {
+                writer.write_u8(0x3F);
+                writer.write_string(channel);
+                writer.write_vec_data(data);
+            }
+            Packet::UpdateHealth {health, food, food_saturation} => {
+                writer.write_u8(0x06);
+                writer.write_f32(*health);
+                writer.write_varint(*food);
+                writer.write_f32(*food_saturation);
+            }
+            Packet::Respawn {dimension, difficulty, gamemode, level_type} => {
+                writer.write_u8(0x07);
+                writer.write_i32(*dimension);
+                writer.write_u8(*difficulty);
+                writer.write_u8(*gamemode);
+                writer.write_string(level_type);
+            }
+            Packet::ChangeGameState {reason, value} => {
+                writer.write_u8(0x2B);
+                writer.write_u8(*reason);
+                writer.write_f32(*value);
+            }
+            Packet::PlayerAbilities {flags, flying_speed, field_of_view} => {
+                writer.write_u8(0x39);
+                writer.write_u8(*flags);
+                writer.write_f32(*flying_speed);
+                writer.write_f32(*field_of_view);
+            }
+            Packet::Animation {entity_id, animation} => {
+                writer.write_u8(0x0B);
+                writer.write_varint(*entity_id);
+                writer.write_u8(*animation);
+            }
+            Packet::SetSlot {window_id, slot, item} => {
+                writer.write_u8(0x2F);
+                writer.write_i8(*window_id);
+                writer.write_i16(*slot);
+                writer.write_slot(item);
+            }
+            Packet::OpenWindow {window_id, window_type, title, slot_count, entity_id} => {
+                writer.write_u8(0x2D);
+                writer.write_u8(*window_id);
+                writer.write_string(window_type);
+                writer.write_string(&title.to_json_string());
+                writer.write_u8(*slot_count);
+                if let Some(entity_id) = entity_id {
+                    writer.write_i32(*entity_id);
+                }
+            }
+            Packet::CloseWindow {window_id} => {
+                writer.write_u8(0x2E);
+                writer.write_u8(*window_id);
+            }
+            Packet::ConfirmTransaction {window_id, action_number, accepted} => {
+                writer.write_u8(0x32);
+                writer.write_i8(*window_id);
+                writer.write_i16(*action_number);
+                writer.write_bool(*accepted);
+            }
+            Packet::EntityVelocity {entity_id, vx, vy, vz} => {
+                writer.write_u8(0x12);
+                writer.write_varint(*entity_id);
+                writer.write_i16(*vx);
+                writer.write_i16(*vy);
+                writer.write_i16(*vz);
+            }
+            Packet::EntityEquipment {entity_id, slot, item} => {
+                writer.write_u8(0x04);
+                writer.write_varint(*entity_id);
+                writer.write_i16(*slot);
+                writer.write_slot(item);
+            }
+            Packet::EntityMetadataPacket {entity_id, metadata} => {
+                writer.write_u8(0x1C);
+                writer.write_varint(*entity_id);
+                metadata.write(&mut writer);
+            }
+            Packet::EntityStatus {entity_id, status} => {
+                writer.write_u8(0x1A);
+                writer.write_i32(*entity_id);
+                writer.write_i8(*status);
+            }
+            Packet::CollectItem {collected_entity_id, collector_entity_id} => {
+                writer.write_u8(0x0D);
+                writer.write_varint(*collected_entity_id);
+                writer.write_varint(*collector_entity_id);
+            }
+            Packet::SpawnObject {entity_id, type_id, x, y, z, pitch, yaw, data, velocity} => {
+                writer.write_u8(0x0E);
+                writer.write_varint(*entity_id);
+                writer.write_i8(*type_id);
+                writer.write_i32(*x);
+                writer.write_i32(*y);
+                writer.write_i32(*z);
+                writer.write_u8(*pitch);
+                writer.write_u8(*yaw);
+                writer.write_i32(*data);
+                if *data != 0 {
+                    let (velocity_x, velocity_y, velocity_z) = velocity.unwrap_or((0, 0, 0));
+                    writer.write_i16(velocity_x);
+                    writer.write_i16(velocity_y);
+                    writer.write_i16(velocity_z);
+                }
+            }
+            Packet::SpawnExperienceOrb {entity_id, x, y, z, count} => {
+                writer.write_u8(0x11);
+                writer.write_varint(*entity_id);
+                writer.write_i32(*x);
+                writer.write_i32(*y);
+                writer.write_i32(*z);
+                writer.write_i16(*count);
+            }
+            Packet::SpawnPainting {entity_id, title, location, direction} => {
+                writer.write_u8(0x10);
+                writer.write_varint(*entity_id);
+                writer.write_string(title);
+                writer.write_position(location);
+                writer.write_u8(*direction);
+            }
+            Packet::NamedSoundEffect {sound, x, y, z, volume, pitch} => {
+                writer.write_u8(0x29);
+                writer.write_string(sound);
+                writer.write_i32(*x);
+                writer.write_i32(*y);
+                writer.write_i32(*z);
+                writer.write_f32(*volume);
+                writer.write_u8(*pitch);
+            }
+            Packet::Particle {particle_id, long_distance, x, y, z, offset_x, offset_y, offset_z, data, count, extra} => {
+                writer.write_u8(0x2A);
+                writer.write_i32(*particle_id);
+                writer.write_bool(*long_distance);
+                writer.write_f32(*x);
+                writer.write_f32(*y);
+                writer.write_f32(*z);
+                writer.write_f32(*offset_x);
+                writer.write_f32(*offset_y);
+                writer.write_f32(*offset_z);
+                writer.write_f32(*data);
+                writer.write_i32(*count);
+                for value in extra {
+                    writer.write_varint(*value);
+                }
+            }
+            Packet::Effect {effect_id, location, data, disable_relative_volume} => {
+                writer.write_u8(0x28);
+                writer.write_i32(*effect_id);
+                writer.write_position(location);
+                writer.write_i32(*data);
+                writer.write_bool(*disable_relative_volume);
+            }
+            Packet::BlockBreakAnimation {entity_id, location, stage} => {
+                writer.write_u8(0x25);
+                writer.write_varint(*entity_id);
+                writer.write_position(location);
+                writer.write_i8(*stage);
+            }
+            Packet::BlockAction {location, action_id, action_param, block_type} => {
+                writer.write_u8(0x24);
+                writer.write_position(location);
+                writer.write_u8(*action_id);
+                writer.write_u8(*action_param);
+                writer.write_varint(*block_type);
+            }
+            Packet::Explosion {x, y, z, radius, records, player_vx, player_vy, player_vz} => {
+                writer.write_u8(0x27);
+                writer.write_f32(*x);
+                writer.write_f32(*y);
+                writer.write_f32(*z);
+                writer.write_f32(*radius);
+                writer.write_i32(records.len() as i32);
+                for (record_x, record_y, record_z) in records {
+                    writer.write_i8(*record_x);
+                    writer.write_i8(*record_y);
+                    writer.write_i8(*record_z);
+                }
+                writer.write_f32(*player_vx);
+                writer.write_f32(*player_vy);
+                writer.write_f32(*player_vz);
+            }
+            Packet::TabCompleteResponse {matches} => {
+                writer.write_u8(0x3A);
+                writer.write_varint(matches.len() as i32);
+                for string in matches {
+                    writer.write_string(string);
+                }
+            }
+            Packet::PlayerListHeaderFooter {header, footer} => {
+                writer.write_u8(0x47);
+                writer.write_string(&header.to_json_string());
+                writer.write_string(&footer.to_json_string());
+            }
+            Packet::ScoreboardObjective {name, mode, value, type_} => {
+                writer.write_u8(0x3B);
+                writer.write_string(name);
+                writer.write_i8(*mode);
+                if *mode != 1 {
+                    writer.write_string(value);
+                    writer.write_string(type_);
+                }
+            }
+            Packet::UpdateScore {name, action, objective, value} => {
+                writer.write_u8(0x3C);
+                writer.write_string(name);
+                writer.write_i8(*action);
+                writer.write_string(objective);
+                if *action != 1 {
+                    writer.write_varint(*value);
+                }
+            }
+            Packet::DisplayScoreboard {position, name} => {
+                writer.write_u8(0x3D);
+                writer.write_i8(*position);
+                writer.write_string(name);
+            }
+            Packet::Teams {name, mode, data} => {
+                writer.write_u8(0x3E);
+                writer.write_string(name);
+                writer.write_i8(*mode);
+                match data {
+                    TeamData::Create {display_name, prefix, suffix, friendly_fire, name_tag_visibility, color, players} => {
+                        writer.write_string(display_name);
+                        writer.write_string(prefix);
+                        writer.write_string(suffix);
+                        writer.write_i8(*friendly_fire);
+                        writer.write_string(name_tag_visibility);
+                        writer.write_i8(*color);
+                        write_team_players(&mut writer, players);
+                    }
+                    TeamData::Remove => {}
+                    TeamData::UpdateInfo {display_name, prefix, suffix, friendly_fire, name_tag_visibility, color} => {
+                        writer.write_string(display_name);
+                        writer.write_string(prefix);
+                        writer.write_string(suffix);
+                        writer.write_i8(*friendly_fire);
+                        writer.write_string(name_tag_visibility);
+                        writer.write_i8(*color);
+                    }
+                    TeamData::AddPlayers {players} => write_team_players(&mut writer, players),
+                    TeamData::RemovePlayers {players} => write_team_players(&mut writer, players)
+                }
+            }
+            Packet::Title {action} => {
+                writer.write_u8(0x45);
+                match action {
+                    TitleAction::SetTitle(title) => {
+                        writer.write_varint(0);
+                        writer.write_string(&title.to_json_string());
+                    }
+                    TitleAction::SetSubtitle(subtitle) => {
+                        writer.write_varint(1);
+                        writer.write_string(&subtitle.to_json_string());
+                    }
+                    TitleAction::SetTimes {fade_in, stay, fade_out} => {
+                        writer.write_varint(2);
+                        writer.write_i32(*fade_in);
+                        writer.write_i32(*stay);
+                        writer.write_i32(*fade_out);
+                    }
+                    TitleAction::Hide => writer.write_varint(3),
+                    TitleAction::Reset => writer.write_varint(4)
+                }
+            }
+            Packet::UpdateSign {location, lines} => {
+                writer.write_u8(0x33);
+                writer.write_position(location);
+                for line in lines.iter() {
+                    writer.write_string(&line.to_json_string());
+                }
+            }
+            Packet::OpenSignEditor {location} => {
+                writer.write_u8(0x36);
+                writer.write_position(location);
+            }
+            Packet::ResourcePackSend {url, hash} => {
+                writer.write_u8(0x48);
+                writer.write_string(url);
+                writer.write_string(hash);
+            }
+            Packet::Map {map_id, scale, icons, columns, rows, x, z, data} => {
+                writer.write_u8(0x34);
+                writer.write_varint(*map_id);
+                writer.write_i8(*scale);
+                writer.write_varint(icons.len() as i32);
+                for icon in icons {
+                    writer.write_u8((icon.icon_type << 4) | (icon.direction & 0x0F));
+                    writer.write_i8(icon.x);
+                    writer.write_i8(icon.z);
+                }
+                writer.write_u8(*columns);
+                if *columns > 0 {
+                    writer.write_u8(*rows);
+                    writer.write_u8(*x);
+                    writer.write_u8(*z);
+                    writer.write_varint(data.len() as i32);
+                    writer.write_data(data);
+                }
+            }
+            Packet::CombatEvent {event} => {
+                writer.write_u8(0x42);
+                match event {
+                    CombatEventKind::EnterCombat => writer.write_varint(0),
+                    CombatEventKind::EndCombat {duration, entity_id} => {
+                        writer.write_varint(1);
+                        writer.write_varint(*duration);
+                        writer.write_i32(*entity_id);
+                    }
+                    CombatEventKind::EntityDead {player_id, entity_id, message} => {
+                        writer.write_varint(2);
+                        writer.write_varint(*player_id);
+                        writer.write_i32(*entity_id);
+                        writer.write_string(&message.to_json_string());
+                    }
+                }
+            }
+            Packet::Camera {camera_id} => {
+                writer.write_u8(0x43);
+                writer.write_varint(*camera_id);
+            }
+            Packet::UseBed {entity_id, location} => {
+                writer.write_u8(0x0A);
+                writer.write_varint(*entity_id);
+                writer.write_position(location);
+            }
+            Packet::Statistics {entries} => {
+                writer.write_u8(0x37);
+                writer.write_varint(entries.len() as i32);
+                for (name, value) in entries {
+                    writer.write_string(name);
+                    writer.write_varint(*value);
+                }
+            }
+            Packet::MapChunkBulk {sky_light_sent, chunks, data} => {
+                writer.write_u8(0x26);
+                writer.write_bool(*sky_light_sent);
+                writer.write_varint(chunks.len() as i32);
+                for chunk in chunks {
+                    writer.write_i32(chunk.chunk_x);
+                    writer.write_i32(chunk.chunk_z);
+                    writer.write_u16(chunk.primary_bitmask);
+                }
+                //Unlike Chunk Data, the payloads are not prefixed by their size
+                writer.write_vec_data(data);
+            }
+            _ => return Err(PacketError::InvalidState)
+        }
+
+        Ok(writer.data)
+    }
+
+    pub fn serialize_length(&self) -> Result<Vec<u8>, PacketError> {
+        let mut data = self.serialize()?;
+        data.splice(0..0, DataWriter::get_varint(data.len() as u32));
+        Ok(data)
+    }
+}
+
+#[test]
+pub fn test_chat_message() {
+    for position in 0..3u8 {
+        let packet = Packet::ChatMessage {component: ChatComponent::new_text("Hello".to_string()), position};
+        let data = packet.serialize().unwrap();
+
+        let mut reader = DataReader::new(&data);
+        assert_eq!(reader.read_varint().unwrap(), 0x02);
+        assert_eq!(reader.read_string().unwrap(), r#"{"text":"Hello"}"#);
+        assert_eq!(reader.read_u8().unwrap(), position);
+        assert_eq!(reader.cursor, data.len());
+    }
+}
+
+#[test]
+pub fn test_server_chat_message() {
+    let mut writer = DataWriter::new();
+    writer.write_string(&"Hello world".to_string());
+    match Packet::read(0x01, &mut DataReader::new(&writer.data), ConnectionState::Play) {
+        Ok(Packet::ServerChatMessage {message}) => assert_eq!(message, "Hello world"),
+        _ => panic!("Couldn't read chat message")
+    }
+
+    let mut writer = DataWriter::new();
+    writer.write_string(&"a".repeat(101));
+    assert!(Packet::read(0x01, &mut DataReader::new(&writer.data), ConnectionState::Play).is_err());
+}
+
+#[test]
+pub fn test_player_position_and_look() {
+    let mut writer = DataWriter::new();
+    writer.write_f64(-12.5);
+    writer.write_f64(65.62000000476837);
+    writer.write_f64(1024.3);
+    writer.write_f32(270.0);
+    writer.write_f32(-45.5);
+    writer.write_bool(true);
+
+    match Packet::read(0x06, &mut DataReader::new(&writer.data), ConnectionState::Play) {
+        Ok(Packet::PlayerPositionAndLook {x, y, z, yaw, pitch, on_ground}) => {
+            assert_eq!((x, y, z), (-12.5, 65.62000000476837, 1024.3));
+            assert_eq!((yaw, pitch), (270.0, -45.5));
+            assert!(on_ground);
+        }
+        _ => panic!("Couldn't read player position and look")
+    }
+}
+
+#[test]
+pub fn test_block_change() {
+    //Stone, torch facing up and red wool
+    let states = [((1 << 4) | 0, vec![0x10]), ((50 << 4) | 5, vec![0xA5, 0x06]), ((35 << 4) | 14, vec![0xBE, 0x04])];
+
+    for (block_state, expected) in states.iter() {
+        let packet = Packet::BlockChange {location: Position {x: 10, y: 64, z: -10}, block_state: *block_state};
+        let data = packet.serialize().unwrap();
+        assert_eq!(data[0], 0x23);
+        assert_eq!(&data[9..], expected.as_slice());
+    }
+}
+
+#[test]
+pub fn test_multi_block_change() {
+    //Records on the chunk corners, right at the boundaries with the neighbour chunks
+    let packet = Packet::MultiBlockChange {chunk_x: -1, chunk_z: 0, records: vec![
+        BlockChangeRecord::new(0, 0, 0, 1 << 4),
+        BlockChangeRecord::new(15, 255, 15, 3 << 4),
+        BlockChangeRecord::new(15, 64, 0, (35 << 4) | 14)
+    ]};
+    let data = packet.serialize().unwrap();
+
+    let mut reader = DataReader::new(&data);
+    assert_eq!(reader.read_varint(), Ok(0x22));
+    assert_eq!(reader.read_i32(), Ok(-1));
+    assert_eq!(reader.read_i32(), Ok(0));
+    assert_eq!(reader.read_varint(), Ok(3));
+    assert_eq!((reader.read_u8(), reader.read_u8(), reader.read_varint()), (Ok(0x00), Ok(0), Ok(1 << 4)));
+    assert_eq!((reader.read_u8(), reader.read_u8(), reader.read_varint()), (Ok(0xFF), Ok(255), Ok(3 << 4)));
+    assert_eq!((reader.read_u8(), reader.read_u8(), reader.read_varint()), (Ok(0xF0), Ok(64), Ok((35 << 4) | 14)));
+    assert_eq!(reader.cursor, data.len());
+}
+
+#[test]
+pub fn test_spawn_player() {
+    use std::str::FromStr;
+
+    let uuid = Uuid::from_str("0ecda338-9eeb-413e-9629-58bd0d552e5e").unwrap();
+    let packet = Packet::SpawnPlayer {
+        entity_id: 300,
+        uuid,
+        x: 10 * 32,
+        y: 64 * 32,
+        z: -5 * 32 - 16,
+        yaw: 64,
+        pitch: 0,
+        current_item: 276,
+        metadata: EntityMetadata::new()
+    };
+    let data = packet.serialize().unwrap();
+
+    let mut reader = DataReader::new(&data);
+    assert_eq!(reader.read_varint(), Ok(0x0C));
+    assert_eq!(reader.read_varint(), Ok(300));
+    assert_eq!(reader.read_uuid().unwrap(), uuid);
+    assert_eq!(reader.read_i32(), Ok(320));
+    assert_eq!(reader.read_i32(), Ok(2048));
+    assert_eq!(reader.read_i32(), Ok(-176));
+    assert_eq!(reader.read_u8(), Ok(64));
+    assert_eq!(reader.read_u8(), Ok(0));
+    assert_eq!(reader.read_u16(), Ok(276));
+    //Empty metadata is just the terminator
+    assert_eq!(reader.read_u8(), Ok(0x7F));
+    assert_eq!(reader.cursor, data.len());
+}
+
+#[test]
+pub fn test_spawn_mob() {
+    //Zombie
+    let packet = Packet::SpawnMob {
+        entity_id: 7,
+        type_id: 54,
+        x: 32,
+        y: 64 * 32,
+        z: -32,
+        yaw: 128,
+        pitch: 0,
+        head_pitch: 128,
+        velocity_x: 0,
+        velocity_y: -1,
+        velocity_z: 0,
+        metadata: EntityMetadata::new()
+    };
+
+    assert_eq!(packet.serialize().unwrap(), vec![
+        0x0F,
+        0x07,
+        54,
+        0x00, 0x00, 0x00, 0x20,
+        0x00, 0x00, 0x08, 0x00,
+        0xFF, 0xFF, 0xFF, 0xE0,
+        128, 0, 128,
+        0x00, 0x00,
+        0xFF, 0xFF,
+        0x00, 0x00,
+        0x7F
+    ]);
+}
+
+#[test]
+pub fn test_destroy_entities() {
+    assert_eq!(Packet::DestroyEntities {entity_ids: vec![]}.serialize().unwrap(), vec![0x13, 0x00]);
+    assert_eq!(Packet::DestroyEntities {entity_ids: vec![1, 127, 128, 300]}.serialize().unwrap(),
+               vec![0x13, 0x04, 0x01, 0x7F, 0x80, 0x01, 0xAC, 0x02]);
+}
+
+#[test]
+pub fn test_entity_relative_move() {
+    use crate::game::position::relative_delta;
+
+    assert_eq!(relative_delta(1.0), Some(32));
+    assert_eq!(relative_delta(-0.5), Some(-16));
+    assert_eq!(relative_delta(3.96875), Some(127));
+    assert_eq!(relative_delta(-4.0), Some(-128));
+    //More than about 4 blocks can't be sent as a relative move
+    assert_eq!(relative_delta(4.0), None);
+    assert_eq!(relative_delta(-10.0), None);
+
+    let packet = Packet::EntityLookAndRelativeMove {
+        entity_id: 5,
+        dx: relative_delta(1.0).unwrap(),
+        dy: relative_delta(0.0).unwrap(),
+        dz: relative_delta(-0.5).unwrap(),
+        yaw: 64,
+        pitch: 192,
+        on_ground: true
+    };
+    assert_eq!(packet.serialize().unwrap(), vec![0x17, 0x05, 32, 0, 0xF0, 64, 192, 0x01]);
+}
+
+#[test]
+pub fn test_entity_teleport() {
+    let packet = Packet::EntityTeleport {
+        entity_id: 1,
+        x: (10000.5 * 32.0) as i32,
+        y: 100 * 32,
+        z: -25000 * 32,
+        yaw: 0,
+        pitch: 0,
+        on_ground: false
+    };
+    let data = packet.serialize().unwrap();
+
+    let mut reader = DataReader::new(&data);
+    assert_eq!(reader.read_varint(), Ok(0x18));
+    assert_eq!(reader.read_varint(), Ok(1));
+    assert_eq!(reader.read_i32(), Ok(320016));
+    assert_eq!(reader.read_i32(), Ok(3200));
+    assert_eq!(reader.read_i32(), Ok(-800000));
+    assert_eq!(reader.read_data_fixed(3).unwrap(), vec![0, 0, 0]);
+    assert_eq!(reader.cursor, data.len());
+}
+
+#[test]
+pub fn test_entity_head_look() {
+    //0, 90, 180 and 270 degrees
+    for head_yaw in [0u8, 64, 128, 192].iter() {
+        assert_eq!(Packet::EntityHeadLook {entity_id: 42, head_yaw: *head_yaw}.serialize().unwrap(), vec![0x19, 42, *head_yaw]);
+    }
+}
+
+#[test]
+pub fn test_player_movement() {
+    let mut writer = DataWriter::new();
+    writer.write_bool(true);
+    match Packet::read(0x03, &mut DataReader::new(&writer.data), ConnectionState::Play) {
+        Ok(Packet::Player {on_ground}) => assert!(on_ground),
+        _ => panic!("Couldn't read player")
+    }
+
+    let mut writer = DataWriter::new();
+    writer.write_f64(0.5);
+    writer.write_f64(64.0);
+    writer.write_f64(-0.5);
+    writer.write_bool(false);
+    match Packet::read(0x04, &mut DataReader::new(&writer.data), ConnectionState::Play) {
+        Ok(Packet::PlayerPosition {x, y, z, on_ground}) => assert_eq!((x, y, z, on_ground), (0.5, 64.0, -0.5, false)),
+        _ => panic!("Couldn't read player position")
+    }
+
+    let mut writer = DataWriter::new();
+    writer.write_f32(90.0);
+    writer.write_f32(-90.0);
+    writer.write_bool(true);
+    match Packet::read(0x05, &mut DataReader::new(&writer.data), ConnectionState::Play) {
+        Ok(Packet::PlayerLook {yaw, pitch, on_ground}) => assert_eq!((yaw, pitch, on_ground), (90.0, -90.0, true)),
+        _ => panic!("Couldn't read player look")
+    }
+
+    //NaN and infinite values are rejected
+    let mut writer = DataWriter::new();
+    writer.write_f64(f64::NAN);
+    writer.write_f64(64.0);
+    writer.write_f64(0.0);
+    writer.write_bool(true);
+    assert!(Packet::read(0x04, &mut DataReader::new(&writer.data), ConnectionState::Play).is_err());
+
+    let mut writer = DataWriter::new();
+    writer.write_f64(0.0);
+    writer.write_f64(64.0);
+    writer.write_f64(0.0);
+    writer.write_f32(f32::INFINITY);
+    writer.write_f32(0.0);
+    writer.write_bool(true);
+    assert!(Packet::read(0x06, &mut DataReader::new(&writer.data), ConnectionState::Play).is_err());
+}
+
+#[test]
+pub fn test_client_settings() {
+    //Settings sent by a vanilla client right after joining
+    let data = [0x05, b'e', b'n', b'_', b'U', b'S', 0x0C, 0x00, 0x01, 0x7F];
+    match Packet::read(0x15, &mut DataReader::new(&data), ConnectionState::Play) {
+        Ok(Packet::ClientSettings {locale, view_distance, chat_mode, chat_colors, displayed_skin_parts}) => {
+            assert_eq!(locale, "en_US");
+            assert_eq!(view_distance, 12);
+            assert_eq!(chat_mode, 0);
+            assert!(chat_colors);
+            assert_eq!(displayed_skin_parts, 0x7F);
+        }
+        _ => panic!("Couldn't read client settings")
+    }
+}
+
+#[test]
+pub fn test_plugin_message() {
+    let mut brand = DataWriter::new();
+    brand.write_string(&"vanilla".to_string());
+
+    let packet = Packet::PluginMessageOut {channel: "MC|Brand".to_string(), data: brand.data.clone()};
+    let data = packet.serialize().unwrap();
+    assert_eq!(data[0], 0x3F);
+
+    match Packet::read(0x17, &mut DataReader::new(&data[1..]), ConnectionState::Play) {
+        Ok(Packet::PluginMessageIn {channel, data}) => {
+            assert_eq!(channel, "MC|Brand");
+            assert_eq!(data, brand.data);
+        }
+        _ => panic!("Couldn't read plugin message")
+    }
+}
+
+#[test]
+pub fn test_update_health() {
+    let full = Packet::UpdateHealth {health: 20.0, food: 20, food_saturation: 5.0}.serialize().unwrap();
+    assert_eq!(full, vec![0x06, 0x41, 0xA0, 0x00, 0x00, 20, 0x40, 0xA0, 0x00, 0x00]);
+
+    let dead = Packet::UpdateHealth {health: 0.0, food: 0, food_saturation: 0.0}.serialize().unwrap();
+    assert_eq!(dead, vec![0x06, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+pub fn test_client_status() {
+    for action_id in 0..3 {
+        match Packet::read(0x16, &mut DataReader::new(&[action_id as u8]), ConnectionState::Play) {
+            Ok(Packet::ClientStatus {action_id: read}) => assert_eq!(read, action_id),
+            _ => panic!("Couldn't read client status")
+        }
+    }
+}
+
+#[test]
+pub fn test_respawn() {
+    //Back to the overworld from the nether
+    let packet = Packet::Respawn {dimension: 0, difficulty: 2, gamemode: 0, level_type: "default".to_string()};
+    let mut expected = vec![0x07, 0, 0, 0, 0, 2, 0, 7];
+    expected.extend_from_slice(b"default");
+    assert_eq!(packet.serialize().unwrap(), expected);
+
+    let nether = Packet::Respawn {dimension: -1, difficulty: 2, gamemode: 0, level_type: "default".to_string()};
+    assert_eq!(nether.serialize().unwrap()[1..5], [0xFF, 0xFF, 0xFF, 0xFF]);
+}
+
+#[test]
+pub fn test_time_update() {
+    let packet = Packet::TimeUpdate {world_age: 24000, time_of_day: 6000};
+    assert_eq!(packet.serialize().unwrap(), vec![0x03, 0, 0, 0, 0, 0, 0, 0x5D, 0xC0, 0, 0, 0, 0, 0, 0, 0x17, 0x70]);
+
+    //Frozen at noon
+    let frozen = Packet::TimeUpdate {world_age: 24000, time_of_day: -6000};
+    assert_eq!(frozen.serialize().unwrap()[9..], [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xE8, 0x90]);
+}
+
+#[test]
+pub fn test_change_game_state() {
+    assert_eq!(Packet::ChangeGameState {reason: 2, value: 0.0}.serialize().unwrap(), vec![0x2B, 2, 0, 0, 0, 0]);
+    //Creative
+    assert_eq!(Packet::ChangeGameState {reason: 3, value: 1.0}.serialize().unwrap(), vec![0x2B, 3, 0x3F, 0x80, 0, 0]);
+}
+
+#[test]
+pub fn test_player_abilities() {
+    //Creative, allowed to fly and flying
+    let packet = Packet::PlayerAbilities {flags: 0x08 | 0x04 | 0x02, flying_speed: 0.05, field_of_view: 0.1};
+    let data = packet.serialize().unwrap();
+    assert_eq!(data[0..2], [0x39, 0x0E]);
+    assert_eq!(data.len(), 10);
+
+    //Player stopped flying
+    let mut writer = DataWriter::new();
+    writer.write_u8(0x0C);
+    writer.write_f32(0.05);
+    writer.write_f32(0.1);
+    match Packet::read(0x13, &mut DataReader::new(&writer.data), ConnectionState::Play) {
+        Ok(Packet::ServerPlayerAbilities {flags, flying_speed, walking_speed}) => {
+            assert_eq!(flags & 0x02, 0);
+            assert_eq!(flags, 0x0C);
+            assert_eq!(flying_speed, 0.05);
+            assert_eq!(walking_speed, 0.1);
+        }
+        _ => panic!("Couldn't read player abilities")
+    }
+}
+
+#[test]
+pub fn test_held_item_change_server() {
+    match Packet::read(0x09, &mut DataReader::new(&[0x00, 0x08]), ConnectionState::Play) {
+        Ok(Packet::HeldItemChangeServer {slot}) => assert_eq!(slot, 8),
+        _ => panic!("Couldn't read held item change")
+    }
+    assert!(Packet::read(0x09, &mut DataReader::new(&[0x00, 0x09]), ConnectionState::Play).is_err());
+    assert!(Packet::read(0x09, &mut DataReader::new(&[0xFF, 0xFF]), ConnectionState::Play).is_err());
+}
+
+#[test]
+pub fn test_animation() {
+    assert!(matches!(Packet::read(0x0A, &mut DataReader::new(&[]), ConnectionState::Play), Ok(Packet::AnimationServer)));
+    assert_eq!(Packet::Animation {entity_id: 300, animation: 0}.serialize().unwrap(), vec![0x0B, 0xAC, 0x02, 0]);
+}
+
+#[test]
+pub fn test_player_digging() {
+    let location = Position {x: 10, y: 64, z: -20};
+    for status in [0i8, 2].iter() {
+        let mut writer = DataWriter::new();
+        writer.write_i8(*status);
+        writer.write_position(&location);
+        writer.write_i8(1);
+        match Packet::read(0x07, &mut DataReader::new(&writer.data), ConnectionState::Play) {
+            Ok(Packet::PlayerDigging {status: read, location: read_location, face}) => {
+                assert_eq!(read, *status);
+                assert_eq!(read_location, location);
+                assert_eq!(face, 1);
+            }
+            _ => panic!("Couldn't read player digging")
+        }
+    }
+}
+
+#[test]
+pub fn test_block_placement() {
+    //Stone placed on top of a block
+    let mut writer = DataWriter::new();
+    writer.write_position(&Position {x: 5, y: 63, z: 5});
+    writer.write_i8(1);
+    writer.write_slot(&Slot {item_id: 1, item_count: Some(64), item_damage: Some(0), nbt: None});
+    writer.data.extend_from_slice(&[8, 15, 8]);
+    match Packet::read(0x08, &mut DataReader::new(&writer.data), ConnectionState::Play) {
+        Ok(Packet::BlockPlacement {location, face, held_item, cursor_x, cursor_y, cursor_z}) => {
+            assert_eq!(location, Position {x: 5, y: 63, z: 5});
+            assert_eq!(face, 1);
+            assert_eq!(held_item.item_id, 1);
+            assert_eq!(held_item.item_count, Some(64));
+            assert_eq!((cursor_x, cursor_y, cursor_z), (8, 15, 8));
+        }
+        _ => panic!("Couldn't read block placement")
+    }
+
+    //Using an empty hand
+    let mut writer = DataWriter::new();
+    writer.write_i64(-1);
+    writer.write_i8(-1);
+    writer.write_i16(-1);
+    writer.data.extend_from_slice(&[0, 0, 0]);
+    match Packet::read(0x08, &mut DataReader::new(&writer.data), ConnectionState::Play) {
+        Ok(Packet::BlockPlacement {location, face, held_item, ..}) => {
+            assert_eq!(location, Position {x: -1, y: -1, z: -1});
+            assert_eq!(face, -1);
+            assert_eq!(held_item.item_id, -1);
+        }
+        _ => panic!("Couldn't read item use")
+    }
+}
+
+#[test]
+pub fn test_window_items() {
+    let mut slots = vec![Slot::empty(); 45];
+    slots[36] = Slot {item_id: 276, item_count: Some(1), item_damage: Some(0), nbt: None};
+    slots[37] = Slot {item_id: 1, item_count: Some(64), item_damage: Some(0), nbt: None};
+    slots[5] = Slot {item_id: 310, item_count: Some(1), item_damage: Some(12), nbt: None};
+
+    let data = Packet::WindowItems {window_id: 0, slots: slots.clone()}.serialize().unwrap();
+    assert_eq!(data[0..4], [0x30, 0, 0, 45]);
+
+    let mut reader = DataReader::new(&data[4..]);
+    for slot in slots.iter() {
+        assert_eq!(reader.read_slot().unwrap(), *slot);
+    }
+    assert_eq!(reader.cursor, data.len() - 4);
+}
+
+#[test]
+pub fn test_set_slot() {
+    let stone = Slot {item_id: 1, item_count: Some(64), item_damage: Some(0), nbt: None};
+
+    //First hotbar slot of the player inventory
+    let data = Packet::SetSlot {window_id: 0, slot: 36, item: stone.clone()}.serialize().unwrap();
+    assert_eq!(data, vec![0x2F, 0, 0, 36, 0, 1, 64, 0, 0, 0]);
+
+    let cursor = Packet::SetSlot {window_id: CURSOR_WINDOW, slot: CURSOR_SLOT, item: Slot::empty()}.serialize().unwrap();
+    assert_eq!(cursor, vec![0x2F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+}
+
+#[test]
+pub fn test_open_window() {
+    let packet = Packet::OpenWindow {
+        window_id: 1,
+        window_type: "minecraft:chest".to_string(),
+        title: ChatComponent::new_text("Chest".to_string()),
+        slot_count: 27,
+        entity_id: None
+    };
+    let mut expected = DataWriter::new();
+    expected.write_u8(0x2D);
+    expected.write_u8(1);
+    expected.write_string(&"minecraft:chest".to_string());
+    expected.write_string(&r#"{"text":"Chest"}"#.to_string());
+    expected.write_u8(27);
+    assert_eq!(packet.serialize().unwrap(), expected.data);
+
+    assert_eq!(Packet::CloseWindow {window_id: 1}.serialize().unwrap(), vec![0x2E, 1]);
+    match Packet::read(0x0D, &mut DataReader::new(&[1]), ConnectionState::Play) {
+        Ok(Packet::CloseWindow {window_id}) => assert_eq!(window_id, 1),
+        _ => panic!("Couldn't read close window")
+    }
+}
+
+#[test]
+pub fn test_confirm_transaction() {
+    let accepted = Packet::ConfirmTransaction {window_id: 0, action_number: 5, accepted: true};
+    assert_eq!(accepted.serialize().unwrap(), vec![0x32, 0, 0, 5, 1]);
+    let rejected = Packet::ConfirmTransaction {window_id: 1, action_number: 300, accepted: false};
+    let data = rejected.serialize().unwrap();
+    assert_eq!(data, vec![0x32, 1, 0x01, 0x2C, 0]);
+
+    match Packet::read(0x0F, &mut DataReader::new(&data[1..]), ConnectionState::Play) {
+        Ok(Packet::ConfirmTransaction {window_id, action_number, accepted}) => {
+            assert_eq!(window_id, 1);
+            assert_eq!(action_number, 300);
+            assert!(!accepted);
+        }
+        _ => panic!("Couldn't read confirm transaction")
+    }
+}
+
+#[test]
+pub fn test_click_window() {
+    let stone = Slot {item_id: 1, item_count: Some(64), item_damage: Some(0), nbt: None};
+    for (button, mode) in [(0i8, 0i8), (0, 1)].iter() {
+        let mut writer = DataWriter::new();
+        writer.write_u8(0);
+        writer.write_i16(36);
+        writer.write_i8(*button);
+        writer.write_i16(7);
+        writer.write_i8(*mode);
+        writer.write_slot(&stone);
+        match Packet::read(0x0E, &mut DataReader::new(&writer.data), ConnectionState::Play) {
+            Ok(Packet::ClickWindow {window_id, slot, button: read_button, action_number, mode: read_mode, clicked_item}) => {
+                assert_eq!(window_id, 0);
+                assert_eq!(slot, 36);
+                assert_eq!(read_button, *button);
+                assert_eq!(action_number, 7);
+                assert_eq!(read_mode, *mode);
+                assert_eq!(clicked_item, stone);
+            }
+            _ => panic!("Couldn't read click window")
+        }
+    }
+}
+
+#[test]
+pub fn test_entity_velocity() {
+    use crate::game::position::velocity;
+
+    //Knockback from a hit
+    let packet = Packet::EntityVelocity {entity_id: 42, vx: velocity(0.4), vy: velocity(0.4), vz: velocity(-0.2)};
+    assert_eq!(packet.serialize().unwrap(), vec![0x12, 42, 0x0C, 0x80, 0x0C, 0x80, 0xF9, 0xC0]);
+
+    assert_eq!(velocity(10.0), 31200);
+    assert_eq!(velocity(-10.0), -31200);
+}
+
+#[test]
+pub fn test_entity_equipment() {
+    let sword = Slot {item_id: 276, item_count: Some(1), item_damage: Some(0), nbt: None};
+    let packet = Packet::EntityEquipment {entity_id: 42, slot: 0, item: sword};
+    assert_eq!(packet.serialize().unwrap(), vec![0x04, 42, 0, 0, 0x01, 0x14, 1, 0, 0, 0]);
+}
+
+#[test]
+pub fn test_entity_metadata_packet() {
+    use crate::game::metadata::MetadataValue;
+
+    //Index 0 is the status byte, 0x01 is on fire
+    let packet = Packet::EntityMetadataPacket {entity_id: 42, metadata: EntityMetadata::new().add(0, MetadataValue::Byte {byte: 0x01})};
+    assert_eq!(packet.serialize().unwrap(), vec![0x1C, 42, 0x00, 0x01, 0x7F]);
+}
+
+#[test]
+pub fn test_entity_status() {
+    //The entity id is an int here, not a varint
+    assert_eq!(Packet::EntityStatus {entity_id: 42, status: 2}.serialize().unwrap(), vec![0x1A, 0, 0, 0, 42, 2]);
+}
+
+#[test]
+pub fn test_collect_item() {
+    assert_eq!(Packet::CollectItem {collected_entity_id: 300, collector_entity_id: 1}.serialize().unwrap(), vec![0x0D, 0xAC, 0x02, 1]);
+}
+
+#[test]
+pub fn test_spawn_object() {
+    //Dropped item at 1, 64, 1
+    let still = Packet::SpawnObject {entity_id: 9, type_id: 2, x: 32, y: 2048, z: 32, pitch: 0, yaw: 0, data: 0, velocity: None};
+    assert_eq!(still.serialize().unwrap(), vec![0x0E, 9, 2, 0, 0, 0, 32, 0, 0, 8, 0, 0, 0, 0, 32, 0, 0, 0, 0, 0, 0]);
+
+    //The velocity is ignored when data is 0
+    let ignored = Packet::SpawnObject {entity_id: 9, type_id: 2, x: 32, y: 2048, z: 32, pitch: 0, yaw: 0, data: 0, velocity: Some((100, 200, 300))};
+    assert_eq!(ignored.serialize().unwrap(), still.serialize().unwrap());
+
+    let thrown = Packet::SpawnObject {entity_id: 9, type_id: 2, x: 32, y: 2048, z: 32, pitch: 0, yaw: 0, data: 1, velocity: Some((100, 200, 300))};
+    let data = thrown.serialize().unwrap();
+    assert_eq!(data.len(), 27);
+    assert_eq!(data[17..], [0, 0, 0, 1, 0, 100, 0, 200, 0x01, 0x2C]);
+}
+
+#[test]
+pub fn test_spawn_experience_orb() {
+    //At 0.5, 64, -0.5
+    let packet = Packet::SpawnExperienceOrb {entity_id: 12, x: 16, y: 64 * 32, z: -16, count: 7};
+    assert_eq!(packet.serialize().unwrap(), vec![0x11, 12, 0, 0, 0, 16, 0, 0, 8, 0, 0xFF, 0xFF, 0xFF, 0xF0, 0, 7]);
+}
+
+#[test]
+pub fn test_spawn_painting() {
+    let location = Position {x: 3, y: 65, z: -7};
+    let packet = Packet::SpawnPainting {entity_id: 20, title: "Kebab".to_string(), location: location.clone(), direction: 0};
+
+    let mut expected = DataWriter::new();
+    expected.write_u8(0x10);
+    expected.write_varint(20);
+    expected.write_string(&"Kebab".to_string());
+    expected.write_position(&location);
+    expected.write_u8(0);
+    assert_eq!(packet.serialize().unwrap(), expected.data);
+}
+
+#[test]
+pub fn test_named_sound_effect() {
+    //At 10, 64, -2
+    let packet = Packet::NamedSoundEffect {sound: "random.levelup".to_string(), x: 80, y: 512, z: -16, volume: 1.0, pitch: 63};
+
+    let mut expected = DataWriter::new();
+    expected.write_u8(0x29);
+    expected.write_string(&"random.levelup".to_string());
+    expected.data.extend_from_slice(&[0, 0, 0, 80, 0, 0, 2, 0, 0xFF, 0xFF, 0xFF, 0xF0, 0x3F, 0x80, 0, 0, 63]);
+    assert_eq!(packet.serialize().unwrap(), expected.data);
+}
+
+#[test]
+pub fn test_particle() {
+    let heart = Packet::Particle {
+        particle_id: 34,
+        long_distance: false,
+        x: 0.5, y: 65.0, z: 0.5,
+        offset_x: 0.2, offset_y: 0.2, offset_z: 0.2,
+        data: 0.0,
+        count: 5,
+        extra: vec![]
+    };
+    let data = heart.serialize().unwrap();
+    assert_eq!(data.len(), 1 + 4 + 1 + 4 * 7 + 4);
+    assert_eq!(data[0..6], [0x2A, 0, 0, 0, 34, 0]);
+    assert_eq!(data[data.len() - 4..], [0, 0, 0, 5]);
+
+    //Dirt dust
+    let blockdust = Packet::Particle {
+        particle_id: 38,
+        long_distance: true,
+        x: 0.5, y: 65.0, z: 0.5,
+        offset_x: 0.0, offset_y: 0.0, offset_z: 0.0,
+        data: 0.1,
+        count: 20,
+        extra: vec![3]
+    };
+    let data = blockdust.serialize().unwrap();
+    assert_eq!(data[5], 1);
+    assert_eq!(data[data.len() - 5..], [0, 0, 0, 20, 3]);
+}
+
+#[test]
+pub fn test_effect() {
+    let location = Position {x: 8, y: 64, z: 8};
+    let packet = Packet::Effect {effect_id: 1000, location: location.clone(), data: 0, disable_relative_volume: false};
+
+    let mut expected = DataWriter::new();
+    expected.data.extend_from_slice(&[0x28, 0, 0, 0x03, 0xE8]);
+    expected.write_position(&location);
+    expected.data.extend_from_slice(&[0, 0, 0, 0, 0]);
+    assert_eq!(packet.serialize().unwrap(), expected.data);
+}
+
+#[test]
+pub fn test_block_break_animation() {
+    let location = Position {x: 1, y: 63, z: 1};
+    for stage in [0i8, 5, 9, -1].iter() {
+        let data = Packet::BlockBreakAnimation {entity_id: 3, location: location.clone(), stage: *stage}.serialize().unwrap();
+        let mut reader = DataReader::new(&data);
+        assert_eq!(reader.read_u8(), Ok(0x25));
+        assert_eq!(reader.read_varint(), Ok(3));
+        assert_eq!(reader.read_position(), Ok(location.clone()));
+        assert_eq!(reader.read_i8(), Ok(*stage));
+    }
+}
+
+#[test]
+pub fn test_block_action() {
+    //Harp note block playing F#
+    let location = Position {x: 0, y: 64, z: 0};
+    let packet = Packet::BlockAction {location: location.clone(), action_id: 0, action_param: 12, block_type: 25};
+
+    let mut expected = DataWriter::new();
+    expected.write_u8(0x24);
+    expected.write_position(&location);
+    expected.data.extend_from_slice(&[0, 12, 25]);
+    assert_eq!(packet.serialize().unwrap(), expected.data);
+}
+
+#[test]
+pub fn test_explosion() {
+    //TNT has a radius of 4
+    let packet = Packet::Explosion {
+        x: 0.5, y: 64.0, z: 0.5,
+        radius: 4.0,
+        records: vec![(0, 0, 0), (1, 0, 0), (0, -1, 0)],
+        player_vx: 0.0, player_vy: 0.5, player_vz: 0.0
+    };
+    let data = packet.serialize().unwrap();
+    assert_eq!(data.len(), 1 + 16 + 4 + 3 * 3 + 12);
+    assert_eq!(data[13..17], [0x40, 0x80, 0, 0]);
+    assert_eq!(data[17..30], [0, 0, 0, 3, 0, 0, 0, 1, 0, 0, 0, 0xFF, 0]);
+}
+
+#[test]
+pub fn test_tab_complete() {
+    let mut writer = DataWriter::new();
+    writer.write_string(&"/give MrP".to_string());
+    writer.write_bool(true);
+    writer.write_position(&Position {x: 1, y: 2, z: 3});
+    match Packet::read(0x14, &mut DataReader::new(&writer.data), ConnectionState::Play) {
+        Ok(Packet::TabComplete {text, has_position, looked_at_block}) => {
+            assert_eq!(text, "/give MrP");
+            assert!(has_position);
+            assert_eq!(looked_at_block, Some(Position {x: 1, y: 2, z: 3}));
+        }
+        _ => panic!("Couldn't read tab complete")
+    }
+
+    let response = Packet::TabCompleteResponse {matches: vec!["MrPiva".to_string(), "MrPotato".to_string()]};
+    let mut expected = DataWriter::new();
+    expected.write_u8(0x3A);
+    expected.write_varint(2);
+    expected.write_string(&"MrPiva".to_string());
+    expected.write_string(&"MrPotato".to_string());
+    assert_eq!(response.serialize().unwrap(), expected.data);
+}
+
+#[test]
+pub fn test_player_list_header_footer() {
+    let header = ChatComponent::new_text("Amethyst".to_string()).color("light_purple").bold(true);
+    let packet = Packet::PlayerListHeaderFooter {header, footer: ChatComponent::new()};
+
+    let mut expected = DataWriter::new();
+    expected.write_u8(0x47);
+    expected.write_string(&r#"{"text":"Amethyst","color":"light_purple","bold":true}"#.to_string());
+    expected.write_string(&r#"{"text":""}"#.to_string());
+    assert_eq!(packet.serialize().unwrap(), expected.data);
+}
+
+#[test]
+pub fn test_scoreboard() {
+    let create = Packet::ScoreboardObjective {name: "kills".to_string(), mode: 0, value: "Kills".to_string(), type_: "integer".to_string()};
+    let mut expected = DataWriter::new();
+    expected.write_u8(0x3B);
+    expected.write_string(&"kills".to_string());
+    expected.write_i8(0);
+    expected.write_string(&"Kills".to_string());
+    expected.write_string(&"integer".to_string());
+    assert_eq!(create.serialize().unwrap(), expected.data);
+
+    let score = Packet::UpdateScore {name: "MrPiva".to_string(), action: 0, objective: "kills".to_string(), value: 3};
+    let mut expected = DataWriter::new();
+    expected.write_u8(0x3C);
+    expected.write_string(&"MrPiva".to_string());
+    expected.write_i8(0);
+    expected.write_string(&"kills".to_string());
+    expected.write_varint(3);
+    assert_eq!(score.serialize().unwrap(), expected.data);
+
+    //Removing doesn't send the value
+    let remove = Packet::UpdateScore {name: "MrPiva".to_string(), action: 1, objective: "kills".to_string(), value: 3};
+    assert_eq!(remove.serialize().unwrap().len(), expected.data.len() - 1);
+
+    let sidebar = Packet::DisplayScoreboard {position: 1, name: "kills".to_string()};
+    assert_eq!(sidebar.serialize().unwrap(), vec![0x3D, 1, 5, b'k', b'i', b'l', b'l', b's']);
+}
+
+#[test]
+pub fn test_teams() {
+    let create = Packet::Teams {name: "red".to_string(), mode: 0, data: TeamData::Create {
+        display_name: "Red".to_string(),
+        prefix: "§c".to_string(),
+        suffix: String::new(),
+        friendly_fire: 0x01,
+        name_tag_visibility: "always".to_string(),
+        color: 12,
+        players: vec!["MrPiva".to_string()]
+    }};
+    let mut expected = DataWriter::new();
+    expected.write_u8(0x3E);
+    expected.write_string(&"red".to_string());
+    expected.write_i8(0);
+    expected.write_string(&"Red".to_string());
+    expected.write_string(&"§c".to_string());
+    expected.write_string(&String::new());
+    expected.write_i8(0x01);
+    expected.write_string(&"always".to_string());
+    expected.write_i8(12);
+    expected.write_varint(1);
+    expected.write_string(&"MrPiva".to_string());
+    assert_eq!(create.serialize().unwrap(), expected.data);
+
+    let add = Packet::Teams {name: "red".to_string(), mode: 3, data: TeamData::AddPlayers {players: vec!["Notch".to_string(), "jeb_".to_string()]}};
+    let mut expected = DataWriter::new();
+    expected.write_u8(0x3E);
+    expected.write_string(&"red".to_string());
+    expected.write_i8(3);
+    expected.write_varint(2);
+    expected.write_string(&"Notch".to_string());
+    expected.write_string(&"jeb_".to_string());
+    assert_eq!(add.serialize().unwrap(), expected.data);
+}
+
+#[test]
+pub fn test_title() {
+    let title = Packet::Title {action: TitleAction::SetTitle(ChatComponent::new_text("Welcome".to_string()).color("gold"))};
+    let mut expected = DataWriter::new();
+    expected.write_u8(0x45);
+    expected.write_varint(0);
+    expected.write_string(&r#"{"text":"Welcome","color":"gold"}"#.to_string());
+    assert_eq!(title.serialize().unwrap(), expected.data);
+
+    let times = Packet::Title {action: TitleAction::SetTimes {fade_in: 10, stay: 70, fade_out: 20}};
+    assert_eq!(times.serialize().unwrap(), vec![0x45, 2, 0, 0, 0, 10, 0, 0, 0, 70, 0, 0, 0, 20]);
+    assert_eq!(Packet::Title {action: TitleAction::Reset}.serialize().unwrap(), vec![0x45, 4]);
+}
+
+#[test]
+pub fn test_use_entity() {
+    match Packet::read(0x02, &mut DataReader::new(&[42, 1]), ConnectionState::Play) {
+        Ok(Packet::UseEntity {target, action: UseEntityAction::Attack}) => assert_eq!(target, 42),
+        _ => panic!("Couldn't read attack")
+    }
+
+    let mut writer = DataWriter::new();
+    writer.write_varint(300);
+    writer.write_varint(2);
+    writer.write_f32(0.25);
+    writer.write_f32(1.5);
+    writer.write_f32(-0.25);
+    match Packet::read(0x02, &mut DataReader::new(&writer.data), ConnectionState::Play) {
+        Ok(Packet::UseEntity {target, action: UseEntityAction::InteractAt {x, y, z}}) => {
+            assert_eq!(target, 300);
+            assert_eq!((x, y, z), (0.25, 1.5, -0.25));
+        }
+        _ => panic!("Couldn't read interact at")
+    }
+
+    assert!(Packet::read(0x02, &mut DataReader::new(&[42, 3]), ConnectionState::Play).is_err());
+}
+
+#[test]
+pub fn test_entity_action() {
+    for (action, expected) in [(0u8, 0), (4, 4)].iter() {
+        match Packet::read(0x0B, &mut DataReader::new(&[7, *action, 0]), ConnectionState::Play) {
+            Ok(Packet::EntityAction {entity_id, action_id, jump_boost}) => {
+                assert_eq!(entity_id, 7);
+                assert_eq!(action_id, *expected);
+                assert_eq!(jump_boost, 0);
+            }
+            _ => panic!("Couldn't read entity action")
+        }
+    }
+}
+
+#[test]
+pub fn test_spectate() {
+    let uuid = Uuid::parse_str("069a79f4-44e9-4726-a5be-fca90e38aaf5").unwrap();
+    match Packet::read(0x18, &mut DataReader::new(uuid.as_bytes()), ConnectionState::Play) {
+        Ok(Packet::Spectate {target}) => assert_eq!(target, uuid),
+        _ => panic!("Couldn't read spectate")
+    }
+    assert!(Packet::read(0x18, &mut DataReader::new(&uuid.as_bytes()[0..15]), ConnectionState::Play).is_err());
+}
+
+#[test]
+pub fn test_update_sign() {
+    let location = Position {x: 4, y: 65, z: -3};
+    let lines = [
+        ChatComponent::new_text("Welcome".to_string()),
+        ChatComponent::new_text("to".to_string()),
+        ChatComponent::new_text("Amethyst".to_string()),
+        ChatComponent::new()
+    ];
+    let data = Packet::UpdateSign {location: location.clone(), lines: lines.clone()}.serialize().unwrap();
+    assert_eq!(data[0], 0x33);
+
+    match Packet::read(0x12, &mut DataReader::new(&data[1..]), ConnectionState::Play) {
+        Ok(Packet::UpdateSign {location: read_location, lines: read_lines}) => {
+            assert_eq!(read_location, location);
+            assert_eq!(read_lines, lines);
+        }
+        _ => panic!("Couldn't read update sign")
+    }
+
+    let mut writer = DataWriter::new();
+    writer.write_position(&location);
+    for _ in 0..4 {
+        writer.write_string(&"not json".to_string());
+    }
+    assert!(Packet::read(0x12, &mut DataReader::new(&writer.data), ConnectionState::Play).is_err());
+}
+
+#[test]
+pub fn test_open_sign_editor() {
+    //x = 1, y = 64, z = -1
+    let packet = Packet::OpenSignEditor {location: Position {x: 1, y: 64, z: -1}};
+    assert_eq!(packet.serialize().unwrap(), vec![0x36, 0x00, 0x00, 0x00, 0x41, 0x03, 0xFF, 0xFF, 0xFF]);
+}
+
+#[test]
+pub fn test_world_border() {
+    let size = Packet::WorldBorder {action: WorldBorderAction::SetSize {radius: 100.0}};
+    assert_eq!(size.serialize().unwrap(), vec![0x44, 0, 0x40, 0x59, 0, 0, 0, 0, 0, 0]);
+
+    let center = Packet::WorldBorder {action: WorldBorderAction::SetCenter {x: 0.5, z: -0.5}};
+    assert_eq!(center.serialize().unwrap(), vec![0x44, 2, 0x3F, 0xE0, 0, 0, 0, 0, 0, 0, 0xBF, 0xE0, 0, 0, 0, 0, 0, 0]);
+
+    //Shrinks in one hour
+    let lerp = Packet::WorldBorder {action: WorldBorderAction::LerpSize {old_radius: 100.0, new_radius: 50.0, speed: 3600000}};
+    let data = lerp.serialize().unwrap();
+    assert_eq!(data[18..], [0x80, 0xDD, 0xDB, 0x01]);
+}
+
+#[test]
+pub fn test_resource_pack() {
+    let hash = "2fd4e1c67a2d28fced849ee1bb76e7391b93eb12".to_string();
+    let packet = Packet::ResourcePackSend {url: "https://example.com/pack.zip".to_string(), hash: hash.clone()};
+    let mut expected = DataWriter::new();
+    expected.write_u8(0x48);
+    expected.write_string(&"https://example.com/pack.zip".to_string());
+    expected.write_string(&hash);
+    assert_eq!(packet.serialize().unwrap(), expected.data);
+
+    let mut writer = DataWriter::new();
+    writer.write_string(&hash);
+    writer.write_varint(3);
+    match Packet::read(0x19, &mut DataReader::new(&writer.data), ConnectionState::Play) {
+        Ok(Packet::ResourcePackStatus {hash: read_hash, result}) => {
+            assert_eq!(read_hash, hash);
+            assert_eq!(result, 3);
+        }
+        _ => panic!("Couldn't read resource pack status")
+    }
+}
+
+#[test]
+pub fn test_map() {
+    let full = Packet::Map {
+        map_id: 0,
+        scale: 0,
+        icons: vec![MapIcon {direction: 8, icon_type: 0, x: 0, z: 0}],
+        columns: 128,
+        rows: 128,
+        x: 0,
+        z: 0,
+        data: vec![34; 128 * 128]
+    };
+    let data = full.serialize().unwrap();
+    assert_eq!(data[0..11], [0x34, 0, 0, 1, 0x08, 0, 0, 128, 128, 0, 0]);
+    //128 * 128 as a VarInt
+    assert_eq!(data[11..14], [0x80, 0x80, 0x01]);
+    assert_eq!(data.len(), 14 + 128 * 128);
+
+    let metadata = Packet::Map {map_id: 3, scale: 2, icons: vec![], columns: 0, rows: 0, x: 0, z: 0, data: vec![]};
+    assert_eq!(metadata.serialize().unwrap(), vec![0x34, 3, 2, 0, 0]);
+}
+
+#[test]
+pub fn test_combat_event() {
+    let message = ChatComponent::new_translate("death.attack.mob", vec![ChatComponent::new_text("MrPiva".to_string()), ChatComponent::new_text("Zombie".to_string())]);
+    let packet = Packet::CombatEvent {event: CombatEventKind::EntityDead {player_id: 1, entity_id: 42, message: message.clone()}};
+
+    let mut expected = DataWriter::new();
+    expected.data.extend_from_slice(&[0x42, 2, 1, 0, 0, 0, 42]);
+    expected.write_string(&message.to_json_string());
+    assert_eq!(packet.serialize().unwrap(), expected.data);
+}
+
+#[test]
+pub fn test_camera() {
+    assert_eq!(Packet::Camera {camera_id: 300}.serialize().unwrap(), vec![0x43, 0xAC, 0x02]);
+}
+
+#[test]
+pub fn test_use_bed() {
+    //x = 1, y = 64, z = -1
+    let packet = Packet::UseBed {entity_id: 5, location: Position {x: 1, y: 64, z: -1}};
+    assert_eq!(packet.serialize().unwrap(), vec![0x0A, 5, 0x00, 0x00, 0x00, 0x41, 0x03, 0xFF, 0xFF, 0xFF]);
+}
+
+#[test]
+pub fn test_statistics() {
+    let packet = Packet::Statistics {entries: vec![("stat.jump".to_string(), 12), ("stat.deaths".to_string(), 300)]};
+    let mut expected = DataWriter::new();
+    expected.write_u8(0x37);
+    expected.write_varint(2);
+    expected.write_string(&"stat.jump".to_string());
+    expected.write_varint(12);
+    expected.write_string(&"stat.deaths".to_string());
+    expected.write_varint(300);
+    assert_eq!(packet.serialize().unwrap(), expected.data);
+}
+
+#[test]
+pub fn test_map_chunk_bulk() {
+    let packet = Packet::MapChunkBulk {
+        sky_light_sent: true,
+        chunks: vec![
+            ChunkMeta {chunk_x: 0, chunk_z: 0, primary_bitmask: 0x0001},
+            ChunkMeta {chunk_x: 1, chunk_z: 0, primary_bitmask: 0x0003}
+        ],
+        data: vec![0xAA, 0xBB]
+    };
+    assert_eq!(packet.serialize().unwrap(), vec![
+        0x26, 0x01, 2,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03,
+        0xAA, 0xBB
+    ]);
+}
+
+#[test]
+pub fn test_packet_error() {
+    assert_eq!(Packet::read(0x05, &mut DataReader::new(&[]), ConnectionState::Status).err(), Some(PacketError::UnknownId {state: ConnectionState::Status, id: 0x05}));
+    assert_eq!(Packet::read(0x01, &mut DataReader::new(&[0x01, 0x02]), ConnectionState::Status).err(), Some(PacketError::UnexpectedEof));
+    assert_eq!(Packet::read(0x00, &mut DataReader::new(&[65, b'a']), ConnectionState::Login).err(), Some(PacketError::StringTooLong));
+    assert_eq!(PacketError::UnknownId {state: ConnectionState::Play, id: 0x4A}.to_string(), "Unknown packet id 0x4A in Play state");
+    assert_eq!(Packet::read(0x00, &mut DataReader::new(&[0xFF; 6]), ConnectionState::Login).err(), Some(PacketError::VarIntTooBig));
+    assert_eq!(Packet::ClientStatus {action_id: 0}.serialize(), Err(PacketError::InvalidState));
+
+    //Every reader error maps to its own variant and back to the same reason
+    for error in [EOF, STRING_TOO_LONG, NEGATIVE_LENGTH, VARINT_TOO_BIG, VARLONG_TOO_BIG].iter() {
+        let packet_error = PacketError::from(*error);
+        assert!(!matches!(packet_error, PacketError::Invalid(_)), "{} isn't mapped", error);
+        assert_eq!(packet_error.reason(), *error);
     }
 }
\ No newline at end of file
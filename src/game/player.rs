@@ -9,11 +9,14 @@ use aes::cipher::StreamCipher;
 use crate::data_writer::DataWriter;
 use std::io::Write;
 use mio::Token;
+use crate::game::packets::PlayerInfoProperties;
 
 pub struct Player {
     pub token: Token,
     pub uuid: Uuid,
-    pub nickname: String
+    pub nickname: String,
+    //Signed skin and cape textures from the session server, empty in offline mode
+    pub properties: Vec<PlayerInfoProperties>
 }
 
 pub type PlayerList = &'static Mutex<Vec<Player>>;
\ No newline at end of file
@@ -26,36 +26,19 @@ use crate::net::network_manager::NetWriter;
 19 - window items
  */
 
-pub fn handle_join(player: &mut Player, net_writer: &NetWriter) {
+pub fn handle_join(player: &mut Player, players: &[Player], net_writer: &NetWriter) {
     println!("Player {} ({}) joined the server", player.nickname, player.uuid);
     let token = player.token;
     net_writer.send_packet(token, Packet::KeepAlive { id: 0 });
-    net_writer.send_packet(token, Packet::JoinGame {
-        entity_id: 0,
-        gamemode: 1,
-        dimension: 0,
-        difficulty: 0,
-        max_players: 255,
-        level_type: "teste".to_string(),
-        reduced_debug_info: false
-    });
-    net_writer.send_packet(token, Packet::SpawnPosition {location: Position {
-        x: 0,
-        y: 50,
-        z: 0
-    }});
-    net_writer.send_packet(token, Packet::HeldItemChange {slot: 0});
-    net_writer.send_packet(token, Packet::PlayerInfo {action_id: 0, players: vec!(PlayerInfoPlayer {
-        uuid: player.uuid.clone(),
-        action: PlayerInfoAction::AddPlayer {
-            name: player.nickname.clone(),
-            properties: vec!(),
-            gamemode: 0,
-            ping: 0,
-            display_name: Option::from(ChatComponent::new_text(player.nickname.clone()))
-        }
-    })});
-    net_writer.send_packet(token, Packet::PlayerPositionAndLook {
+    //JoinGame, SpawnPosition and HeldItemChange were already sent by the login handler
+    //The new player gets the whole tab list, the others only its entry
+    let mut entries = vec!(player_info_entry(player));
+    entries.extend(players.iter().map(player_info_entry));
+    net_writer.send_packet(token, Packet::PlayerInfo {action_id: 0, players: entries});
+    if let Err(e) = net_writer.broadcast(players, Packet::PlayerInfo {action_id: 0, players: vec!(player_info_entry(player))}) {
+        println!("Couldn't add {} to the tab list: {}", player.nickname, e);
+    }
+    net_writer.send_packet(token, Packet::ClientPlayerPositionAndLook {
         x: 0.0,
         y: 50.0,
         z: 0.0,
@@ -66,25 +49,8 @@ pub fn handle_join(player: &mut Player, net_writer: &NetWriter) {
     net_writer.send_packet(token, Packet::WorldBorder {action: WorldBorderAction::SetSize {radius: 100f64}});
     net_writer.send_packet(token, Packet::TimeUpdate {world_age: 0, time_of_day: 12000});
 
-    let bitmask = 0b0000000000001000 as u16;
-    let mut blocks = [[[0u16; 16]; 16]; 16];
     let stone = (1 << 4) | 0;
-    let torch = (50 << 4) | 5;
-    let dirt = (3 << 4) | 0;
-
-    for z in 0..16 {
-        for x in 0..16 {
-            blocks[0][z][x] = stone;
-        }
-    }
-
-    net_writer.send_packet(token, Packet::ChunkData {
-        bitmask,
-        ground_up_continuous: true,
-        x: 0,
-        y: 0,
-        data: write_chunk(&blocks, 16, 16)
-    });
+    net_writer.send_packet(token, flat_chunk(0, 0, 3, stone, 1));
 
     net_writer.send_packet(token, Packet::KeepAlive {id: 4});
 
@@ -121,6 +87,53 @@ pub fn handle_join(player: &mut Player, net_writer: &NetWriter) {
     // )});
 }
 
+//Removes the player that left from the others' tab list
+pub fn handle_quit(player: &Player, players: &[Player], net_writer: &NetWriter) {
+    println!("Player {} disconnected", player.nickname);
+    let packet = Packet::PlayerInfo {action_id: 4, players: vec!(PlayerInfoPlayer {
+        uuid: player.uuid.clone(),
+        action: PlayerInfoAction::RemovePlayer
+    })};
+    if let Err(e) = net_writer.broadcast(players, packet) {
+        println!("Couldn't remove {} from the tab list: {}", player.nickname, e);
+    }
+}
+
+//Tab list entry with the player's skin, if it has one
+pub fn player_info_entry(player: &Player) -> PlayerInfoPlayer {
+    PlayerInfoPlayer {
+        uuid: player.uuid.clone(),
+        action: PlayerInfoAction::AddPlayer {
+            name: player.nickname.clone(),
+            properties: player.properties.clone(),
+            gamemode: 0,
+            ping: 0,
+            display_name: Option::from(ChatComponent::new_text(player.nickname.clone()))
+        }
+    }
+}
+
+//Chunk column with a single section (0 to 15), filled with one block state from the section bottom up to the height
+pub fn flat_chunk(chunk_x: i32, chunk_z: i32, section: u8, block_state: u16, height: usize) -> Packet {
+    assert!(section < 16, "A chunk column only has 16 sections");
+    let mut blocks = [[[0u16; 16]; 16]; 16];
+    for y in 0..height.min(16) {
+        for z in 0..16 {
+            for x in 0..16 {
+                blocks[y][z][x] = block_state;
+            }
+        }
+    }
+
+    Packet::ChunkData {
+        chunk_x,
+        chunk_z,
+        ground_up_continuous: true,
+        primary_bitmask: 1 << section,
+        data: write_chunk(&blocks, 15, 15)
+    }
+}
+
 pub fn write_chunk_light(blocks: &[[[u16; 16]; 16]; 16], block_light: &[u8; 2048], sky_light: &[u8; 2048]) -> Vec<u8> {
     let mut writer = DataWriter::new();
     for y in 0..16 {
@@ -159,4 +172,51 @@ pub fn write_chunk(blocks: &[[[u16; 16]; 16]; 16], block_light: u8, sky_light: u
     }
 
     writer.data
+}
+
+#[test]
+pub fn test_join_and_quit_tab_list() {
+    use mio::Token;
+    use uuid::Uuid;
+    use std::sync::mpsc::channel;
+    use crate::net::network_manager::{NetProtocol, SerializedPacket};
+
+    let (sender, receiver) = channel();
+    let net_writer = NetWriter {writer: sender};
+
+    let others = vec!(Player {token: Token(1), uuid: Uuid::new_v4(), nickname: "Steve".to_string(), properties: vec!()});
+    let mut alex = Player {token: Token(2), uuid: Uuid::new_v4(), nickname: "Alex".to_string(), properties: vec!()};
+
+    //Only the PlayerInfo packets broadcasted to Steve matter here
+    let player_info = |receiver: &std::sync::mpsc::Receiver<NetProtocol>| -> Vec<SerializedPacket> {
+        receiver.try_iter().filter_map(|message| match message {
+            NetProtocol::SendSerialized {token: Token(1), packet} => Some(packet),
+            _ => None
+        }).collect()
+    };
+
+    handle_join(&mut alex, &others, &net_writer);
+    let added = Packet::PlayerInfo {action_id: 0, players: vec!(player_info_entry(&alex))};
+    assert_eq!(player_info(&receiver), vec!(SerializedPacket::play(&added).unwrap()));
+
+    handle_quit(&alex, &others, &net_writer);
+    let removed = Packet::PlayerInfo {action_id: 4, players: vec!(PlayerInfoPlayer {uuid: alex.uuid, action: PlayerInfoAction::RemovePlayer})};
+    assert_eq!(player_info(&receiver), vec!(SerializedPacket::play(&removed).unwrap()));
+}
+
+#[test]
+pub fn test_flat_chunk() {
+    let stone = 1 << 4;
+    let data = flat_chunk(-1, 2, 3, stone, 1).serialize().unwrap();
+
+    //Id, chunk x and z, ground up continuous and the bitmask as an unsigned short instead of a varint
+    assert_eq!(&data[0..12], &[0x21, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x02, 0x01, 0x00, 0x08]);
+    //Blocks (2 bytes each), block light, sky light and biomes of one section
+    let length = 4096 * 2 + 2048 + 2048 + 256;
+    assert_eq!(&data[12..14], DataWriter::get_varint(length as u32).as_slice());
+    assert_eq!(data.len(), 14 + length);
+
+    //Blocks are little endian, only the bottom layer is filled
+    assert_eq!(&data[14..16], &[0x10, 0x00]);
+    assert_eq!(&data[14 + 256 * 2..14 + 256 * 2 + 2], &[0x00, 0x00]);
 }
\ No newline at end of file
@@ -1,3 +1,4 @@
+#[derive(Debug, Clone, PartialEq)]
 pub struct Position {
     pub x: i32,
     pub y: i16,
@@ -8,4 +9,28 @@ impl Position {
     pub const fn encode(&self) -> i64 {
         ((self.x as i64 & 0x3FFFFFF) << 38) | ((self.y as i64 & 0xFFF) << 26) | (self.z as i64 & 0x3FFFFFF)
     }
+
+    //Shifting left then right (arithmetic) sign-extends each field
+    pub const fn decode(value: i64) -> Position {
+        Position {
+            x: (value >> 38) as i32,
+            y: ((value << 26) >> 52) as i16,
+            z: ((value << 38) >> 38) as i32
+        }
+    }
+}
+
+//Relative entity moves are fixed-point bytes (value * 32), so deltas bigger than about 4 blocks
+//don't fit and need an entity teleport instead
+pub fn relative_delta(delta: f64) -> Option<i8> {
+    let fixed = (delta * 32.0).round();
+    if fixed < i8::MIN as f64 || fixed > i8::MAX as f64 {
+        return None;
+    }
+    Some(fixed as i8)
+}
+
+//Velocities are sent in 1/8000 of a block per tick, clamped like vanilla to 3.9 blocks per tick
+pub fn velocity(blocks_per_tick: f64) -> i16 {
+    (blocks_per_tick.max(-3.9).min(3.9) * 8000.0) as i16
 }
\ No newline at end of file
@@ -23,8 +23,37 @@ fn main() {
 
     let writer = NetWriter {writer: game_writer};
 
-    net::network_manager::start(net_writer, net_reader);
-
-    // net::https::test();
-    game::engine::start(players, writer, game_reader).join().expect("couldn't join thread in main thread");
+    //Offline mode skips Mojang authentication and encryption, useful for testing and LAN servers
+    let offline_mode = std::env::args().any(|arg| arg == "--offline");
+
+    //Bind address can be changed with --ip <address> and --port <port>
+    let ip = arg_value("--ip");
+    let port = arg_value("--port").map(|port| port.parse::<u16>().expect("Invalid port"));
+    let addr = net::network_manager::bind_address(ip.as_deref(), port).expect("Invalid bind address");
+
+    let network = net::network_manager::start(addr, players, net_writer, net_reader, offline_mode);
+
+    game::engine::start(players, writer.clone(), game_reader);
+
+    //Typing stop in the console closes the server, disconnecting everyone first
+    std::thread::Builder::new().name("Console Thread".to_string()).spawn(move || {
+        let stdin = std::io::stdin();
+        let mut line = String::new();
+        while stdin.read_line(&mut line).map_or(false, |read| read > 0) {
+            if line.trim() == "stop" {
+                writer.shutdown(ChatComponent::new_text("Server closing".to_string()));
+                return;
+            }
+            line.clear();
+        }
+    }).expect("couldn't start the console thread");
+
+    network.join().expect("couldn't join thread in main thread");
+}
+
+//Returns the value after an argument, like the port in --port 25566
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != name);
+    args.next()?;
+    args.next()
 }
\ No newline at end of file
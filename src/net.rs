@@ -2,3 +2,6 @@ pub mod login_handler;
 pub mod packet_listener;
 pub mod https;
 pub mod network_manager;
+pub mod compression;
+pub mod favicon;
+pub mod framer;
\ No newline at end of file
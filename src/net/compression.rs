@@ -0,0 +1,85 @@
+use flate2::write::ZlibEncoder;
+use flate2::read::ZlibDecoder;
+use flate2::Compression;
+use std::io::{Write, Read};
+use std::io;
+use crate::data_writer::DataWriter;
+use crate::data_reader::DataReader;
+
+//Biggest uncompressed packet accepted, same as vanilla
+const MAX_DATA_LENGTH: i32 = 2097152;
+
+//Frames a serialized packet (id and fields) in the compressed format: packet length, data length and data
+//Packets smaller than the threshold are sent uncompressed with a data length of 0
+pub fn compress(data: &[u8], threshold: i32) -> io::Result<Vec<u8>> {
+    let mut writer = DataWriter::new();
+
+    if data.len() >= threshold as usize {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+
+        writer.write_varint(data.len() as i32);
+        writer.write_vec_data(&compressed);
+    } else {
+        writer.write_varint(0);
+        writer.write_data(data);
+    }
+
+    writer.data.splice(0..0, DataWriter::get_varint(writer.data.len() as u32));
+    Ok(writer.data)
+}
+
+//Reads a compressed packet without its packet length, returning the packet id and fields
+pub fn decompress(data: &[u8]) -> Option<Vec<u8>> {
+    let mut reader = DataReader::new(data);
+    let data_length = reader.read_varint().ok()?;
+    let body = &data[reader.cursor..];
+
+    if data_length == 0 {
+        return Some(body.to_vec());
+    }
+    if data_length < 0 || data_length > MAX_DATA_LENGTH {
+        return None;
+    }
+
+    let mut decompressed = Vec::with_capacity(data_length as usize);
+    ZlibDecoder::new(body).take(data_length as u64).read_to_end(&mut decompressed).ok()?;
+
+    //Data length must match the real size of the decompressed data
+    if decompressed.len() != data_length as usize {
+        return None;
+    }
+
+    Some(decompressed)
+}
+
+#[test]
+pub fn test_compression_below_threshold() {
+    let data = vec![0x02u8; 100];
+    let framed = compress(&data, 256).unwrap();
+
+    let mut reader = DataReader::new(&framed);
+    let length = reader.read_varint().unwrap();
+    assert_eq!(length as usize, framed.len() - reader.cursor);
+    //Data length 0 means it wasn't compressed
+    assert_eq!(reader.read_varint().unwrap(), 0);
+    assert_eq!(&framed[reader.cursor..], data.as_slice());
+
+    let start = framed.len() - length as usize;
+    assert_eq!(decompress(&framed[start..]).unwrap(), data);
+}
+
+#[test]
+pub fn test_compression_above_threshold() {
+    let data = vec![0x02u8; 1000];
+    let framed = compress(&data, 256).unwrap();
+
+    let mut reader = DataReader::new(&framed);
+    let length = reader.read_varint().unwrap();
+    assert_eq!(length as usize, framed.len() - reader.cursor);
+    assert_eq!(reader.read_varint().unwrap(), 1000);
+
+    let start = framed.len() - length as usize;
+    assert_eq!(decompress(&framed[start..]).unwrap(), data);
+}
\ No newline at end of file
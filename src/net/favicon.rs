@@ -0,0 +1,45 @@
+use rustc_serialize::base64::{ToBase64, STANDARD};
+
+//Same file name vanilla uses for the server list icon
+pub const FAVICON_PATH: &str = "server-icon.png";
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+//Loads the PNG icon and encodes it as the data URI sent in the status response, load it once and reuse it
+pub fn load(path: &str) -> Result<String, &'static str> {
+    let data = std::fs::read(path).map_err(|_e| "Couldn't read the favicon file")?;
+    encode(&data)
+}
+
+pub fn encode(data: &[u8]) -> Result<String, &'static str> {
+    //Signature, then the IHDR chunk (length, "IHDR", width and height)
+    if data.len() < 24 || data[0..8] != PNG_SIGNATURE || &data[12..16] != b"IHDR" {
+        return Err("Favicon isn't a PNG image");
+    }
+
+    let width = u32::from_be_bytes([data[16], data[17], data[18], data[19]]);
+    let height = u32::from_be_bytes([data[20], data[21], data[22], data[23]]);
+    if width != 64 || height != 64 {
+        return Err("Favicon must be 64x64");
+    }
+
+    Ok(format!("data:image/png;base64,{}", data.to_base64(STANDARD)))
+}
+
+#[test]
+pub fn test_favicon() {
+    let mut png = PNG_SIGNATURE.to_vec();
+    png.extend_from_slice(&13u32.to_be_bytes());
+    png.extend_from_slice(b"IHDR");
+    png.extend_from_slice(&64u32.to_be_bytes());
+    png.extend_from_slice(&64u32.to_be_bytes());
+    png.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let favicon = encode(&png).unwrap();
+    assert!(favicon.starts_with("data:image/png;base64,iVBORw0KGgo"));
+    assert_eq!(favicon.len(), "data:image/png;base64,".len() + (png.len() + 2) / 3 * 4);
+
+    //Wrong size
+    png[16..20].copy_from_slice(&32u32.to_be_bytes());
+    assert!(encode(&png).is_err());
+}
\ No newline at end of file
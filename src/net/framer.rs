@@ -0,0 +1,136 @@
+use std::borrow::Cow;
+use crate::net::compression;
+use crate::net::network_manager::RawPacket;
+
+//Biggest packet length accepted, the max value of a 3 bytes varint like vanilla
+const MAX_PACKET_LENGTH: i32 = 2097151;
+
+//Keeps the bytes received from a client until a whole packet has arrived,
+//since TCP may split a packet (even its length) between reads or join many in one
+pub struct PacketFramer {
+    buffer: Vec<u8>
+}
+
+impl PacketFramer {
+    pub fn new() -> PacketFramer {
+        PacketFramer {buffer: Vec::new()}
+    }
+
+    //Data must be already decrypted
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    //Returns every complete packet received, incomplete ones stay buffered for the next read
+    pub fn read_packets(&mut self, compressed: bool) -> Result<Vec<RawPacket<'static>>, &'static str> {
+        let mut packets = Vec::new();
+        let mut index = 0usize;
+        while let Some((start, end)) = self.next_frame(index)? {
+            packets.push(raw_packet(&self.buffer[start..end], compressed)?);
+            index = end;
+        }
+        self.buffer.drain(..index);
+        Ok(packets)
+    }
+
+    //Start and end of the body of the packet at the index, if it already arrived
+    fn next_frame(&self, index: usize) -> Result<Option<(usize, usize)>, &'static str> {
+        let mut length = 0i32;
+        let mut length_size = 0usize;
+        loop {
+            if length_size == 3 {
+                return Err("Packet length is too big");
+            }
+            let byte = match self.buffer.get(index + length_size) {Some(t) => *t, None => return Ok(None)};
+            length |= ((byte & 0b01111111) as i32) << (7 * length_size);
+            length_size += 1;
+            if (byte & 0b10000000) == 0 {
+                break;
+            }
+        }
+        if length == 0 || length > MAX_PACKET_LENGTH {
+            return Err("Invalid packet length");
+        }
+
+        let start = index + length_size;
+        let end = start + length as usize;
+        if end > self.buffer.len() {
+            return Ok(None);
+        }
+        Ok(Some((start, end)))
+    }
+}
+
+//Separates the id from a packet body, decompressing it first if needed
+fn raw_packet(body: &[u8], compressed: bool) -> Result<RawPacket<'static>, &'static str> {
+    let body = if compressed {
+        compression::decompress(body).ok_or("Couldn't decompress packet")?
+    } else {
+        body.to_vec()
+    };
+
+    let mut id = 0i32;
+    for i in 0..5 {
+        let byte = *body.get(i).ok_or("Invalid packet id")?;
+        id |= ((byte & 0b01111111) as i32) << (7 * i);
+        if (byte & 0b10000000) == 0 {
+            return Ok(RawPacket {id, data: Cow::Owned(body[i + 1..].to_vec())});
+        }
+    }
+    Err("Invalid packet id")
+}
+
+#[test]
+pub fn test_framer_byte_by_byte() {
+    use crate::game::packets::Packet;
+    use crate::game::chat::ChatComponent;
+
+    let chat = |text: String| Packet::ChatMessage {component: ChatComponent::new_text(text), position: 0};
+    let data = chat("MrPiva".to_string()).serialize_length().unwrap();
+    let mut framer = PacketFramer::new();
+
+    for byte in &data[..data.len() - 1] {
+        framer.feed(&[*byte]);
+        assert!(framer.read_packets(false).unwrap().is_empty());
+    }
+    framer.feed(&data[data.len() - 1..]);
+
+    let packets = framer.read_packets(false).unwrap();
+    assert_eq!(packets.len(), 1);
+    assert_eq!(packets[0].id, 0x02);
+    assert_eq!(&*packets[0].data, &data[2..]);
+    assert!(framer.buffer.is_empty());
+}
+
+#[test]
+pub fn test_framer_combined() {
+    use crate::game::packets::Packet;
+    use crate::game::chat::ChatComponent;
+
+    let chat = |text: String| Packet::ChatMessage {component: ChatComponent::new_text(text), position: 0};
+    //Big enough to have a 2 bytes length, split in the middle of it
+    let big = chat("a".repeat(300)).serialize_length().unwrap();
+    let small = chat("MrPiva".to_string()).serialize_length().unwrap();
+    let mut data = [small.clone(), big.clone(), small.clone()].concat();
+    data.extend_from_slice(&big[..1]);
+
+    let mut framer = PacketFramer::new();
+    framer.feed(&data);
+    assert_eq!(framer.read_packets(false).unwrap().len(), 3);
+
+    framer.feed(&big[1..]);
+    let packets = framer.read_packets(false).unwrap();
+    assert_eq!(packets.len(), 1);
+    assert_eq!(&*packets[0].data, &big[3..]);
+
+    //Compressed packets are only decompressed once complete
+    let body = chat("b".repeat(300)).serialize().unwrap();
+    let compressed = compression::compress(&body, 256).unwrap();
+    framer.feed(&compressed[..10]);
+    assert!(framer.read_packets(true).unwrap().is_empty());
+    framer.feed(&compressed[10..]);
+    assert_eq!(&*framer.read_packets(true).unwrap()[0].data, &body[1..]);
+
+    framer.feed(&[0xFF, 0xFF, 0xFF, 0x01]);
+    assert!(framer.read_packets(false).is_err());
+}
\ No newline at end of file
@@ -1,31 +1,65 @@
-use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
-use std::net::TcpStream;
-use std::io::{Write, Read};
+use openssl::ssl::{SslConnector, SslMethod};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::io::{Write, Read, Error, ErrorKind};
+use std::time::Duration;
 
-pub struct HttpsConnection {
+//Used for the connection, each write and each read, so a stuck server can't hang the request forever
+const TIMEOUT: Duration = Duration::from_secs(5);
 
+pub struct HttpsResponse {
+    pub status: u16,
+    pub body: String
+}
+
+//Blocking HTTP/1.0 GET over TLS, the connection is closed by the server after the response
+pub fn get(host: &str, path: &str) -> Result<HttpsResponse, Error> {
+    let connector = SslConnector::builder(SslMethod::tls_client())
+        .map_err(|e| Error::new(ErrorKind::Other, e))?
+        .build();
+
+    let addr = (host, 443).to_socket_addrs()?.next()
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "Host not found"))?;
+    let tcp = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+    tcp.set_read_timeout(Some(TIMEOUT))?;
+    tcp.set_write_timeout(Some(TIMEOUT))?;
+
+    let mut stream = connector.connect(host, tcp)
+        .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+
+    stream.write_all(format!("GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n", path, host).as_bytes())?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    parse_response(&response)
+}
+
+fn parse_response(response: &[u8]) -> Result<HttpsResponse, Error> {
+    let response = String::from_utf8_lossy(response);
+
+    //Status line is "HTTP/1.x <code> <reason>"
+    let status = response.split_whitespace().nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Malformed HTTP status line"))?;
+
+    let body = match response.find("\r\n\r\n") {
+        Some(index) => response[index + 4..].to_string(),
+        None => String::new()
+    };
+
+    Ok(HttpsResponse {status, body})
 }
 
 #[test]
-pub fn test() {
-    let mut connector = SslConnector::builder(SslMethod::tls_client()).unwrap();
-    connector.set_verify(SslVerifyMode::NONE);
-    let connector = connector.build();
-
-    let mut stream = connector.connect(
-        "sessionserver.mojang.com",
-        TcpStream::connect("sessionserver.mojang.com:443").unwrap()
-    ).unwrap();
-
-    println!("{:?}", stream);
-
-    let get = b"GET /session/minecraft/profile/0ecda3389eeb413e962958bd0d552e5e HTTP/1.0\
-    \nHost: sessionserver.mojang.com\
-    \nConnection: keep-alive\
-    \nDate: Tue, 02 Mar 2021 20:39:17
-    \r\n\r\n";
-    stream.write_all(get).unwrap();
-    let mut res = vec![];
-    stream.read_to_end(&mut res).unwrap();
-    println!("{}", String::from_utf8_lossy(&res));
+pub fn test_parse_response() {
+    let response = parse_response(b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{\"id\":\"abc\"}").unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, r#"{"id":"abc"}"#);
+
+    //Mojang answers 204 without a body when the player isn't authenticated
+    let response = parse_response(b"HTTP/1.1 204 No Content\r\n\r\n").unwrap();
+    assert_eq!(response.status, 204);
+    assert_eq!(response.body, "");
+
+    assert!(parse_response(b"").is_err());
+    assert!(parse_response(b"HTTP/1.1 OK\r\n\r\n").is_err());
 }
\ No newline at end of file
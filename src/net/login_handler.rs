@@ -1,4 +1,4 @@
-use crate::game::packets::Packet;
+use crate::game::packets::{Packet, PlayerInfoProperties};
 use json::JsonValue;
 use json::number::Number;
 use crate::game::chat::ChatComponent;
@@ -7,14 +7,27 @@ use openssl::pkey::Private;
 use rand::{Rng, thread_rng};
 use cfb8::Cfb8;
 use aes::Aes128;
-use uuid::Uuid;
+use uuid::{Uuid, Builder, Variant, Version};
 use aes::cipher::NewStreamCipher;
 use regex::Regex;
 use rustc_serialize::hex::ToHex;
 use openssl::sha::Sha1;
+use openssl::hash::{hash, MessageDigest};
 use std::str::FromStr;
-use crate::net::network_manager::{RawPacket, PlayerLoginClient, ConnectionState};
+use crate::net::network_manager::{RawPacket, PlayerLoginClient, ConnectionState, COMPRESSION_THRESHOLD};
+use mio::Token;
 use crate::data_reader::DataReader;
+use crate::data_writer::DataWriter;
+use crate::net::https;
+use crate::game::player::Player;
+use crate::game::position::Position;
+
+const VERSION_NAME: &str = "1.8.9";
+const PROTOCOL_VERSION: i32 = 47;
+const MAX_PLAYERS: i32 = 10;
+const MOTD: &str = "Amethyst Minecraft Server";
+//Max players shown when hovering the player count in the server list
+const SAMPLE_SIZE: usize = 12;
 
 pub enum HandleResult {
     Disconnect(&'static str),
@@ -26,14 +39,18 @@ pub fn handle(packets: Vec<RawPacket>, client: &mut PlayerLoginClient) -> Handle
     let mut result = HandleResult::None;
 
     for raw in packets {
-        let packet = match Packet::read(raw.id, &mut DataReader::new(raw.data), client.state) {Some(t) => t, None => continue};
+        let packet = match Packet::read(raw.id, &mut DataReader::new(&raw.data), client.state) {Ok(t) => t, Err(e) => {
+            println!("Invalid packet from {}: {}", client.connection.identifier, e);
+            result = disconnect(client, e.reason());
+            break;
+        }};
         match packet {
             Packet::Handshake {next_state, protocol_version, server_address, server_port} => {
                 match next_state {
                     1 => client.state = ConnectionState::Status,
                     2 => client.state = ConnectionState::Login,
                     _ => {
-                        result = HandleResult::Disconnect("Invalid handshake next state");
+                        result = disconnect(client, "Invalid handshake next state");
                         break;
                     }
                 }
@@ -41,36 +58,58 @@ pub fn handle(packets: Vec<RawPacket>, client: &mut PlayerLoginClient) -> Handle
             Packet::StatusRequest => {
                 let mut json = JsonValue::new_object();
                 let mut version = JsonValue::new_object();
-                version["name"] = JsonValue::String("1.8.9".to_owned());
-                version["protocol"] = JsonValue::Number(Number::from(47 as u8));
+                version["name"] = JsonValue::String(VERSION_NAME.to_owned());
+                version["protocol"] = JsonValue::Number(Number::from(PROTOCOL_VERSION));
                 json["version"] = version;
                 let mut players = JsonValue::new_object();
-                players["max"] = JsonValue::Number(Number::from(10 as u8));
-                players["online"] = JsonValue::Number(Number::from(0 as u8));
+                players["max"] = JsonValue::Number(Number::from(MAX_PLAYERS));
+                {
+                    let online = client.players.lock().unwrap();
+                    players["online"] = JsonValue::Number(Number::from(online.len()));
+                    players["sample"] = player_sample(&online, SAMPLE_SIZE);
+                }
                 json["players"] = players;
-                json["description"] = ChatComponent::new_text("Amethyst Minecraft Server".to_owned()).to_json();
+                json["description"] = ChatComponent::new_text(MOTD.to_owned()).to_json();
+                if let Some(favicon) = &client.favicon {
+                    json["favicon"] = JsonValue::String(favicon.to_string());
+                }
                 client.write(Packet::StatusResponse {json});
             }
             Packet::Ping {ping} => client.write(Packet::Pong {pong: ping}),
             Packet::LoginStart {nickname} => {
-                client.verify_token = Some(thread_rng().gen::<[u8; 4]>());
-                client.write(Packet::EncryptionRequest {server: String::new(), public_key: get_publick_key().clone(), verify_token: client.verify_token.unwrap().clone()});
+                if !is_valid_username(&nickname) {
+                    result = disconnect(client, "Invalid username");
+                    break;
+                }
                 client.connection.identifier = nickname.clone();
+
+                //Offline mode skips the encryption and Mojang authentication
+                if client.offline_mode {
+                    client.uuid = Some(offline_uuid(&nickname));
+                    client.nickname = Some(nickname);
+
+                    result = login_success(client);
+                    break;
+                }
+
+                client.verify_token = Some(thread_rng().gen::<[u8; 4]>());
+                client.write(Packet::EncryptionRequest {server: String::new(), public_key: client.key.get_publick_key().clone(), verify_token: client.verify_token.unwrap().clone()});
                 client.nickname = Some(nickname)
             }
             Packet::EncryptionResponse {verify_token, shared_secret} => {
-                let rsa = get_rsa();
+                let key = client.key.clone();
+                let rsa = key.get_rsa();
                 let mut decrypted_verify_token = [0 as u8; 128];
                 match rsa.private_decrypt(&verify_token, &mut decrypted_verify_token, Padding::PKCS1) {
                     Ok(_t) => {},
                     Err(_e) => {
-                        result = HandleResult::Disconnect("Invalid verify token");
+                        result = disconnect(client, "Invalid verify token");
                         break;
                     }
                 };
 
-                if !decrypted_verify_token[0..4].eq(&client.verify_token.unwrap()) {
-                    result = HandleResult::Disconnect("Wrong verify token");
+                if client.verify_token.map_or(true, |token| !decrypted_verify_token[0..4].eq(&token)) {
+                    result = disconnect(client, "Wrong verify token");
                     break;
                 }
 
@@ -78,10 +117,15 @@ pub fn handle(packets: Vec<RawPacket>, client: &mut PlayerLoginClient) -> Handle
                 let shared_secret_length = match rsa.private_decrypt(&shared_secret, &mut decrypted_shared_secret, Padding::PKCS1) {
                     Ok(t) => t,
                     Err(_e) => {
-                        result = HandleResult::Disconnect("Invalid shared secret");
+                        result = disconnect(client, "Invalid shared secret");
                         break;
                     }
                 };
+                //AES-128 needs a 16 bytes key
+                if shared_secret_length != 16 {
+                    result = disconnect(client, "Invalid shared secret");
+                    break;
+                }
                 let shared_secret = &decrypted_shared_secret[0..shared_secret_length];
 
                 client.encode = Some(Cfb8::<Aes128>::new_var(shared_secret, shared_secret).unwrap());
@@ -90,56 +134,21 @@ pub fn handle(packets: Vec<RawPacket>, client: &mut PlayerLoginClient) -> Handle
                 let mut sha1 = Sha1::new();
                 sha1.update(b"");
                 sha1.update(&shared_secret);
-                sha1.update(&rsa.public_key_to_der().unwrap());
-
-                // let response = match reqwest::blocking::Client::new().get(&format!("https://sessionserver.mojang.com/session/minecraft/hasJoined?username={}&serverId={}", client.nickname.as_ref().unwrap(), hex_digest(sha1)))
-                //     .send() {
-                //     Ok(ok) => ok,
-                //     Err(e) => {
-                //         println!("Error while contacting sessionserver.mojang.com to login a player: {}, {}", client.nickname.as_ref().unwrap(), e);
-                //         return HandleResult::Disconnect("Couldn't decrypt shared secret")
-                //     }
-                // };
-                // let response_code = response.status().as_u16();
-                // if response_code == 204 {
-                //     return HandleResult::Disconnect("Client not authenticated.")
-                // }
-                // let json = match response.text() {
-                //     Ok(ok) => {
-                //         match json::parse(&ok) {
-                //             Ok(ok) => ok,
-                //             Err(e) => {
-                //                 println!("Error while parsing login response to json: {}, {}", client.nickname.as_ref().unwrap(), e);
-                //                 return HandleResult::Disconnect("An error occured while contacting Mojang.")
-                //             }
-                //         }
-                //     },
-                //     Err(e) => {
-                //         println!("Error while parsing login response to text: {}, {}", client.nickname.as_ref().unwrap(), e);
-                //         return HandleResult::Disconnect("An error occured while contacting Mojang.")
-                //     }
-                // };
-                //
-                // let data = match parse_json(json) {
-                //     Some(t) => t,
-                //     None => {
-                //         println!("Error while parsing login response data to text: {}", client.nickname.as_ref().unwrap());
-                //         return HandleResult::Disconnect("An error occured while contacting Mojang.")
-                //     }
-                // };
-
-                client.uuid = Some(Uuid::default());
-                client.nickname = Some("britney bitch".to_string());
-
-                client.write(Packet::LoginSuccess {
-                    uuid: client.uuid.clone().unwrap(),
-                    nickname: client.nickname.clone().unwrap()
-                });
-                result = HandleResult::Login;
+                sha1.update(key.get_publick_key());
+
+                //Mojang may take a while to answer, so the request runs in its own thread and
+                //the network manager finishes the login when the result arrives
+                let nickname = client.nickname.clone().unwrap();
+                let path = format!("/session/minecraft/hasJoined?username={}&serverId={}", nickname, hex_digest(sha1));
+                let token = client.connection.token;
+                let sender = client.auth_sender.clone();
+                std::thread::Builder::new().name("Authentication Thread".to_string()).spawn(move || {
+                    sender.send(AuthResult {token, profile: authenticate(&nickname, &path)});
+                }).expect("couldn't start the authentication thread");
                 break;
             }
             _ => {
-                result = HandleResult::Disconnect("Unknown packet for this connection state");
+                result = disconnect(client, "Unknown packet for this connection state");
                 break;
             }
         }
@@ -148,7 +157,138 @@ pub fn handle(packets: Vec<RawPacket>, client: &mut PlayerLoginClient) -> Handle
     result
 }
 
-fn parse_json(mut json: JsonValue) -> Option<(Uuid, String)> {
+//Profile of a player authenticated by Mojang, or the reason to disconnect it
+pub struct AuthResult {
+    pub token: Token,
+    pub profile: Result<(Uuid, String, Vec<PlayerInfoProperties>), &'static str>
+}
+
+//Asks Mojang if the player joined with the server id, blocking until it answers or times out
+fn authenticate(nickname: &str, path: &str) -> Result<(Uuid, String, Vec<PlayerInfoProperties>), &'static str> {
+    let response = match https::get("sessionserver.mojang.com", path) {
+        Ok(t) => t,
+        Err(e) => {
+            println!("Error while contacting sessionserver.mojang.com to login a player: {}, {}", nickname, e);
+            return Err("An error occured while contacting Mojang.");
+        }
+    };
+    if response.status == 204 {
+        return Err("Client not authenticated.");
+    }
+    let json = match json::parse(&response.body) {
+        Ok(t) => t,
+        Err(e) => {
+            println!("Error while parsing login response to json: {}, {}", nickname, e);
+            return Err("An error occured while contacting Mojang.");
+        }
+    };
+
+    match parse_json(json) {
+        Some(t) => Ok(t),
+        None => {
+            println!("Error while parsing login response data: {}", nickname);
+            Err("An error occured while contacting Mojang.")
+        }
+    }
+}
+
+//Finishes the login of a client after its authentication thread answered
+pub fn finish_authentication(client: &mut PlayerLoginClient, profile: Result<(Uuid, String, Vec<PlayerInfoProperties>), &'static str>) -> HandleResult {
+    let (uuid, name, properties) = match profile {
+        Ok(t) => t,
+        Err(reason) => return disconnect(client, reason)
+    };
+
+    client.uuid = Some(uuid);
+    client.nickname = Some(name);
+    client.properties = properties;
+
+    login_success(client)
+}
+
+//Sends the reason to the client and closes its connection, the network manager then drops the client
+fn disconnect(client: &mut PlayerLoginClient, reason: &'static str) -> HandleResult {
+    client.disconnect(ChatComponent::new_text(reason.to_owned()));
+    HandleResult::Disconnect(reason)
+}
+
+//Minecraft usernames are 3 to 16 letters, digits or underscores
+fn is_valid_username(nickname: &str) -> bool {
+    (3..=16).contains(&nickname.len()) && nickname.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn player_sample(players: &[Player], max: usize) -> JsonValue {
+    let mut sample = JsonValue::new_array();
+    for player in players.iter().take(max) {
+        let mut entry = JsonValue::new_object();
+        entry["name"] = JsonValue::String(player.nickname.clone());
+        entry["id"] = JsonValue::String(player.uuid.to_hyphenated().to_string());
+        sample.push(entry).unwrap();
+    }
+    sample
+}
+
+//Clients older than 1.7 and some ping tools send 0xFE (0x01) instead of a handshake
+pub fn is_legacy_ping(data: &[u8]) -> bool {
+    data.first() == Some(&0xFE)
+}
+
+//Legacy kick packet (0xFF) with the status fields separated by \0, as a UTF-16BE string prefixed by its length in characters
+pub fn legacy_ping_response(online: usize) -> Vec<u8> {
+    let status = format!("§1\0{}\0{}\0{}\0{}\0{}", PROTOCOL_VERSION, VERSION_NAME, MOTD, online, MAX_PLAYERS);
+    let characters: Vec<u16> = status.encode_utf16().collect();
+
+    let mut writer = DataWriter::new();
+    writer.write_u8(0xFF);
+    writer.write_u16(characters.len() as u16);
+    for character in characters {
+        writer.write_u16(character);
+    }
+
+    writer.data
+}
+
+//Enables the compression, if configured, and finishes the login
+fn login_success(client: &mut PlayerLoginClient) -> HandleResult {
+    if COMPRESSION_THRESHOLD >= 0 {
+        client.write(Packet::SetCompression {threshold: COMPRESSION_THRESHOLD});
+        client.compression_threshold = Some(COMPRESSION_THRESHOLD);
+    }
+
+    client.write(Packet::LoginSuccess {
+        uuid: client.uuid.clone().unwrap(),
+        nickname: client.nickname.clone().unwrap()
+    });
+    join_game(client);
+
+    HandleResult::Login
+}
+
+//Sends the minimum a client needs to spawn into a world and switches it to the Play state
+//Defaults: entity id 0, creative (gamemode 1), overworld (dimension 0), peaceful (difficulty 0),
+//default level type, spawn at 0, 50, 0 and the first hotbar slot selected
+pub fn join_game(client: &mut PlayerLoginClient) {
+    client.write(Packet::JoinGame {
+        entity_id: 0,
+        gamemode: 1,
+        dimension: 0,
+        difficulty: 0,
+        max_players: 255,
+        level_type: "default".to_string(),
+        reduced_debug_info: false
+    });
+    client.write(Packet::SpawnPosition {location: Position {
+        x: 0,
+        y: 50,
+        z: 0
+    }});
+    client.write(Packet::HeldItemChange {slot: 0});
+
+    client.state = ConnectionState::Play;
+}
+
+//Reads the uuid, name and properties (skin and cape textures) from the hasJoined response
+fn parse_json(mut json: JsonValue) -> Option<(Uuid, String, Vec<PlayerInfoProperties>)> {
     let uuid = match json["id"].as_str() {
         Some(t) => t,
         None => return None
@@ -163,23 +303,51 @@ fn parse_json(mut json: JsonValue) -> Option<(Uuid, String)> {
         None => return None
     };
 
-    return Some((uuid, name));
+    let mut properties = Vec::new();
+    for property in json["properties"].members_mut() {
+        let name = property["name"].take_string()?;
+        let value = property["value"].take_string()?;
+        //Only sent signed, unless the server asked for unsigned=true
+        let signature = property["signature"].take_string();
+        properties.push(PlayerInfoProperties {name, value, signature});
+    }
+
+    return Some((uuid, name, properties));
 }
 
-pub static mut RSA: Option<Rsa<Private>> = None;
-pub static mut PUBLIC_KEY: Option<Vec<u8>> = None;
+//Same as Java's UUID.nameUUIDFromBytes("OfflinePlayer:<nickname>"), the UUID vanilla gives to offline players
+fn offline_uuid(nickname: &str) -> Uuid {
+    let digest = hash(MessageDigest::md5(), format!("OfflinePlayer:{}", nickname).as_bytes()).expect("Couldn't hash offline player nickname");
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest);
 
-#[inline]
-fn get_rsa() -> &'static Rsa<Private> {
-    unsafe {
-        return RSA.as_ref().unwrap();
-    }
+    Builder::from_bytes(bytes)
+        .set_variant(Variant::RFC4122)
+        .set_version(Version::Md5)
+        .build()
+}
+
+//Server RSA keypair, generated once at startup and shared by all the login clients
+pub struct ServerKey {
+    rsa: Rsa<Private>,
+    public_key: Vec<u8>
 }
 
-#[inline]
-fn get_publick_key() -> &'static Vec<u8> {
-    unsafe {
-        return PUBLIC_KEY.as_ref().unwrap();
+impl ServerKey {
+    pub fn generate() -> ServerKey {
+        let rsa = Rsa::generate(1024).expect("Couldn't generate RSA server key");
+        let public_key = rsa.public_key_to_der().expect("Couldn't generate RSA server public key");
+        ServerKey {rsa, public_key}
+    }
+
+    #[inline]
+    pub fn get_rsa(&self) -> &Rsa<Private> {
+        &self.rsa
+    }
+
+    #[inline]
+    pub fn get_publick_key(&self) -> &Vec<u8> {
+        &self.public_key
     }
 }
 
@@ -208,4 +376,171 @@ fn two_complement(bytes: &mut [u8; 20]) {
             bytes[i] = bytes[i] + 1;
         }
     }
+}
+
+#[test]
+pub fn test_legacy_ping() {
+    //Sent by 1.6 clients: ping, ping payload and the MC|PingHost plugin message
+    assert!(is_legacy_ping(&[0xFE, 0x01, 0xFA, 0x00, 0x0B]));
+    assert!(!is_legacy_ping(&[0x10, 0x00, 0x2F]));
+
+    let response = legacy_ping_response(3);
+    assert_eq!(response[0], 0xFF);
+    let length = u16::from_be_bytes([response[1], response[2]]) as usize;
+    assert_eq!(response.len(), 3 + length * 2);
+
+    let characters: Vec<u16> = response[3..].chunks(2).map(|x| u16::from_be_bytes([x[0], x[1]])).collect();
+    let status = String::from_utf16(&characters).unwrap();
+    let fields: Vec<&str> = status.split('\0').collect();
+    assert_eq!(fields, vec!["§1", "47", "1.8.9", "Amethyst Minecraft Server", "3", "10"]);
+}
+
+#[test]
+pub fn test_player_sample() {
+    use mio::Token;
+
+    let players = vec![
+        Player {token: Token(1), uuid: Uuid::from_str("0ecda338-9eeb-413e-9629-58bd0d552e5e").unwrap(), nickname: "MrPiva".to_string(), properties: vec![]},
+        Player {token: Token(2), uuid: Uuid::from_str("069a79f4-44e9-4726-a5be-fca90e38aaf5").unwrap(), nickname: "Notch".to_string(), properties: vec![]},
+        Player {token: Token(3), uuid: Uuid::new_v4(), nickname: "Steve".to_string(), properties: vec![]}
+    ];
+
+    let sample = player_sample(&players, 2);
+    assert_eq!(sample.len(), 2);
+    assert_eq!(sample[0]["name"], "MrPiva");
+    assert_eq!(sample[0]["id"], "0ecda338-9eeb-413e-9629-58bd0d552e5e");
+    assert_eq!(sample[1]["name"], "Notch");
+    assert_eq!(sample[1]["id"], "069a79f4-44e9-4726-a5be-fca90e38aaf5");
+}
+
+#[test]
+pub fn test_valid_username() {
+    assert!(is_valid_username("MrPiva"));
+    assert!(is_valid_username("Player_123"));
+    assert!(is_valid_username("abc"));
+    assert!(!is_valid_username("ab"));
+    assert!(!is_valid_username("ThisNameIsTooLong"));
+    assert!(!is_valid_username("Mr Piva"));
+    assert!(!is_valid_username("name&serverId=x"));
+    assert!(!is_valid_username("Jöhn"));
+}
+
+#[test]
+pub fn test_too_long_username() {
+    use std::io::Read;
+    use std::sync::Arc;
+    use crate::net::network_manager::login_client_pair;
+
+    let (mut client, mut peer) = login_client_pair(Arc::new(ServerKey::generate()));
+    let mut writer = DataWriter::new();
+    writer.write_string(&"ThisNameIsTooLong".to_string());
+
+    let result = handle(vec![RawPacket {id: 0x00, data: writer.data.into()}], &mut client);
+    assert!(matches!(result, HandleResult::Disconnect("Invalid username")));
+
+    let mut data = Vec::new();
+    peer.read_to_end(&mut data).unwrap();
+    let mut reader = DataReader::new(&data);
+    reader.read_varint().unwrap();
+    assert_eq!(reader.read_varint().unwrap(), 0x00);
+    assert_eq!(reader.read_string().unwrap(), r#"{"text":"Invalid username"}"#);
+}
+
+#[test]
+pub fn test_wrong_verify_token() {
+    use std::io::Read;
+    use std::sync::Arc;
+    use crate::net::network_manager::login_client_pair;
+
+    let key = Arc::new(ServerKey::generate());
+    let (mut client, mut peer) = login_client_pair(key.clone());
+
+    //Encryption response with a verify token encrypted correctly, but not the one the server sent
+    let mut encrypted = [0u8; 128];
+    let length = key.get_rsa().public_encrypt(&[4, 3, 2, 1], &mut encrypted, Padding::PKCS1).unwrap();
+    let mut writer = DataWriter::new();
+    writer.write_varint(length as i32);
+    writer.write_data(&encrypted[0..length]);
+    writer.write_varint(length as i32);
+    writer.write_data(&encrypted[0..length]);
+
+    let result = handle(vec![RawPacket {id: 0x01, data: writer.data.into()}], &mut client);
+    assert!(matches!(result, HandleResult::Disconnect("Wrong verify token")));
+
+    let mut data = Vec::new();
+    peer.read_to_end(&mut data).unwrap();
+    let mut reader = DataReader::new(&data);
+    assert_eq!(reader.read_varint().unwrap() as usize, data.len() - 1);
+    assert_eq!(reader.read_varint().unwrap(), 0x00);
+    assert_eq!(reader.read_string().unwrap(), r#"{"text":"Wrong verify token"}"#);
+}
+
+#[test]
+pub fn test_invalid_shared_secret() {
+    use std::io::Read;
+    use std::sync::Arc;
+    use crate::net::network_manager::login_client_pair;
+
+    let key = Arc::new(ServerKey::generate());
+    let (mut client, mut peer) = login_client_pair(key.clone());
+
+    //Right verify token, but an 8 bytes shared secret that can't be an AES-128 key
+    let mut writer = DataWriter::new();
+    for data in [&[0x2Au8; 8][..], &[1, 2, 3, 4][..]].iter() {
+        let mut encrypted = [0u8; 128];
+        let length = key.get_rsa().public_encrypt(data, &mut encrypted, Padding::PKCS1).unwrap();
+        writer.write_varint(length as i32);
+        writer.write_data(&encrypted[0..length]);
+    }
+
+    let result = handle(vec![RawPacket {id: 0x01, data: writer.data.into()}], &mut client);
+    assert!(matches!(result, HandleResult::Disconnect("Invalid shared secret")));
+    assert!(client.encode.is_none());
+
+    let mut data = Vec::new();
+    peer.read_to_end(&mut data).unwrap();
+    let mut reader = DataReader::new(&data);
+    reader.read_varint().unwrap();
+    assert_eq!(reader.read_varint().unwrap(), 0x00);
+    assert_eq!(reader.read_string().unwrap(), r#"{"text":"Invalid shared secret"}"#);
+}
+
+#[test]
+pub fn test_parse_session_response() {
+    let json = json::parse(r#"{
+        "id": "069a79f444e94726a5befca90e38aaf5",
+        "name": "Notch",
+        "properties": [{"name": "textures", "value": "eyJ0aW1lc3RhbXAiOjB9", "signature": "c2lnbmF0dXJl"}]
+    }"#).unwrap();
+
+    let (uuid, name, properties) = parse_json(json).unwrap();
+    assert_eq!(uuid.to_hyphenated().to_string(), "069a79f4-44e9-4726-a5be-fca90e38aaf5");
+    assert_eq!(name, "Notch");
+    assert_eq!(properties.len(), 1);
+    assert_eq!(properties[0].name, "textures");
+    assert_eq!(properties[0].value, "eyJ0aW1lc3RhbXAiOjB9");
+    assert_eq!(properties[0].signature.as_deref(), Some("c2lnbmF0dXJl"));
+
+    //Responses without properties are still valid
+    let json = json::parse(r#"{"id": "069a79f444e94726a5befca90e38aaf5", "name": "Notch"}"#).unwrap();
+    assert!(parse_json(json).unwrap().2.is_empty());
+}
+
+#[test]
+pub fn test_finish_authentication() {
+    use std::sync::Arc;
+    use crate::net::network_manager::login_client_pair;
+
+    let key = Arc::new(ServerKey::generate());
+    let (mut client, _peer) = login_client_pair(key.clone());
+    let result = finish_authentication(&mut client, Err("Client not authenticated."));
+    assert!(matches!(result, HandleResult::Disconnect("Client not authenticated.")));
+
+    //The name is the one Mojang answered, with its right capitalization
+    let (mut client, _peer) = login_client_pair(key);
+    let uuid = Uuid::new_v4();
+    let result = finish_authentication(&mut client, Ok((uuid, "mrpiva".to_string(), Vec::new())));
+    assert!(matches!(result, HandleResult::Login));
+    assert_eq!(client.uuid, Some(uuid));
+    assert_eq!(client.nickname, Some("mrpiva".to_string()));
 }
\ No newline at end of file
@@ -1,38 +1,55 @@
 use crate::net::login_handler;
+use crate::net::compression;
+use crate::net::favicon;
+use crate::net::framer::PacketFramer;
 use mio::net::{TcpListener, TcpStream};
 use mio::{Events, Poll, Token, Interest};
 use std::time::{Duration, Instant};
-use std::net::{SocketAddr, Shutdown};
+use std::net::{SocketAddr, Shutdown, IpAddr};
 use mio::event::Source;
 use std::io::{Read, ErrorKind, Write};
+use std::io;
 use crate::data_reader::DataReader;
 use cfb8::Cfb8;
 use aes::Aes128;
 use uuid::Uuid;
 use std::collections::HashMap;
-use crate::game::packets::Packet;
+use crate::game::packets::{Packet, PlayerInfoProperties, PacketError};
 use crate::data_writer::DataWriter;
-use openssl::rsa::Rsa;
 use aes::cipher::StreamCipher;
-use crate::net::login_handler::HandleResult;
+use crate::net::login_handler::{HandleResult, ServerKey, AuthResult};
 use crate::game::chat::ChatComponent;
-use std::sync::mpsc::{Receiver, Sender};
+use crate::game::player::{PlayerList, Player};
+use std::sync::mpsc::{Receiver, Sender, channel};
 use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::borrow::Cow;
+use std::path::Path;
+use rand::{Rng, thread_rng};
 use crate::net::network_manager::DisconnectReason::{IOError, Timeout};
 
-//Server address
-const ADDR: &str = "127.0.0.1:25565";
+//Default bind address and port, all interfaces
+pub const DEFAULT_IP: &str = "0.0.0.0";
+pub const DEFAULT_PORT: u16 = 25565;
 
 //Token for epoll identification
 const SERVER_TOKEN: Token = Token(0);
 
 const BUFFER_SIZE: usize = 4096;
 
+//Packets with this size or bigger are compressed, -1 disables the compression
+pub const COMPRESSION_THRESHOLD: i32 = 256;
+
+//Clients that don't answer a keep alive in this time are disconnected
+const KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct Connection {
     pub token: Token,
     pub stream: TcpStream,
     pub addr: SocketAddr,
-    pub identifier: String
+    pub identifier: String,
+    //Bytes of packets that didn't fully arrive yet
+    pub framer: PacketFramer
 }
 
 pub struct PlayerLoginClient {
@@ -42,13 +59,35 @@ pub struct PlayerLoginClient {
     pub verify_token: Option<[u8; 4]>,
     pub encode: Option<Cfb8<Aes128>>,
     pub decode: Option<Cfb8<Aes128>>,
-    pub uuid: Option<Uuid>
+    pub uuid: Option<Uuid>,
+    pub properties: Vec<PlayerInfoProperties>,
+    pub offline_mode: bool,
+    pub compression_threshold: Option<i32>,
+    pub key: Arc<ServerKey>,
+    pub favicon: Option<Arc<String>>,
+    pub players: PlayerList,
+    //Where the authentication thread sends the Mojang response
+    pub auth_sender: Sender<AuthResult>
 }
 
 impl PlayerLoginClient {
     pub fn write(&mut self, packet: Packet) {
         //Serialize
-        let mut data = match packet.serialize_length() {Some(t) => t, None => return};
+        let mut data = match packet.serialize() {
+            Ok(t) => t,
+            Err(e) => {
+                println!("Couldn't serialize a packet to {}: {}", self.connection.identifier, e);
+                return;
+            }
+        };
+        //Add length prefix and compress
+        data = match frame(data, self.compression_threshold) {
+            Ok(t) => t,
+            Err(e) => {
+                println!("Couldn't compress a packet to {}: {}", self.connection.identifier, e);
+                return;
+            }
+        };
         //Encrypt
         match &mut self.encode {
             Some(encode) => encode.encrypt(&mut data),
@@ -58,60 +97,134 @@ impl PlayerLoginClient {
         self.connection.stream.write(&data);
     }
 
-    pub fn write_dc(&mut self, reason: String) {
-        //Serialize
-        let packet = Packet::DisconnectLogin {reason: ChatComponent::new_text(reason)};
-        let mut serialized = match packet.serialize_length() {Some(t) => t, None => return};
-        //Write
-        self.connection.stream.write(&serialized);
+    //Writes the disconnect packet of the current state and closes the socket
+    //Handshaking and Status have no disconnect packet, so the socket is just closed
+    pub fn disconnect(&mut self, reason: ChatComponent) {
+        match self.state {
+            ConnectionState::Login => self.write(Packet::DisconnectLogin {reason}),
+            ConnectionState::Play => self.write(Packet::DisconnectPlay {reason}),
+            _ => {}
+        }
+        self.connection.stream.flush();
+        self.connection.stream.shutdown(Shutdown::Both);
     }
 
     pub fn shutdown(&mut self, reason: String, poll: &Poll) {
-        if let ConnectionState::Status = self.state {
-            self.write_dc(reason);
-        }
-        self.connection.stream.flush();
         poll.registry().deregister(&mut self.connection.stream);
-        self.connection.stream.shutdown(Shutdown::Both);
+        self.disconnect(ChatComponent::new_text(reason));
+    }
+
+    //Decrypts the received data if the encryption is enabled and returns the packets that fully arrived
+    pub fn read_packets(&mut self, mut data: Vec<u8>) -> Result<Vec<RawPacket<'static>>, &'static str> {
+        match &mut self.decode {
+            Some(decode) => decode.decrypt(&mut data),
+            None => {}
+        }
+        self.connection.framer.feed(&data);
+        self.connection.framer.read_packets(self.compression_threshold.is_some())
     }
 }
 
 pub struct PlayerClient {
     connection: Connection,
-    encode: Cfb8<Aes128>,
-    decode: Cfb8<Aes128>,
-    keep_alive: Instant
+    encode: Option<Cfb8<Aes128>>,
+    decode: Option<Cfb8<Aes128>>,
+    compression_threshold: Option<i32>,
+    keep_alive: KeepAlive,
+    //Encrypted packets waiting for the next flush
+    outgoing: Vec<u8>,
+    //Registered for writable events, because the last flush didn't send everything
+    writable_interest: bool
 }
 
 impl PlayerClient {
-    pub fn write(&mut self, packet: Packet) {
-        //Serialize
-        let mut data = match packet.serialize_length() {Some(t) => t, None => return};
-        //Encrypt
-        self.encode.encrypt(&mut data);
-        //Write
-        self.connection.stream.write(&data);
+    pub fn write<P: Into<OutgoingPacket>>(&mut self, packet: P) {
+        if let Err(e) = self.queue(packet) {
+            println!("Couldn't send a packet to {}: {}", self.connection.identifier, e);
+        }
+        self.flush();
+    }
+
+    //Adds the packet to the outgoing buffer, it's only sent to the socket by flush
+    pub fn queue<P: Into<OutgoingPacket>>(&mut self, packet: P) -> Result<(), PacketError> {
+        let data = match packet.into() {
+            //Serialize, add length prefix and compress
+            OutgoingPacket::Packet(packet) => match frame(packet.serialize()?, self.compression_threshold) {
+                Ok(t) => t,
+                Err(e) => {
+                    println!("Couldn't compress a packet to {}: {}", self.connection.identifier, e);
+                    return Err(PacketError::Compression);
+                }
+            },
+            //Already framed, only copied to be encrypted for this client
+            OutgoingPacket::Serialized(packet) if packet.compression_threshold == self.compression_threshold => packet.data.to_vec(),
+            //Framed with a compression threshold this client doesn't use
+            OutgoingPacket::Serialized(_) => return Err(PacketError::InvalidState)
+        };
+        self.queue_raw(data);
+        Ok(())
+    }
+
+    //Encrypts and buffers data that is already framed
+    //CFB8 is a stream cipher, so encrypting each packet in order is the same as encrypting the whole buffer
+    fn queue_raw(&mut self, mut data: Vec<u8>) {
+        match &mut self.encode {
+            Some(encode) => encode.encrypt(&mut data),
+            None => {}
+        }
+        self.outgoing.extend_from_slice(&data);
+    }
+
+    //Writes the queued packets at once, what doesn't fit in the socket buffer stays queued
+    //The data is already encrypted, so it must never be dropped or the cipher goes out of sync
+    pub fn flush(&mut self) {
+        while !self.outgoing.is_empty() {
+            match self.connection.stream.write(&self.outgoing) {
+                Ok(0) => break,
+                Ok(written) => {self.outgoing.drain(..written);}
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    println!("An error occured while writing to {}'s stream: {}", self.connection.identifier, e);
+                    break;
+                }
+            }
+        }
         self.connection.stream.flush();
     }
 
+    //Waits for the socket to be writable only while there's data left to flush
+    pub fn update_interest(&mut self, poll: &Poll) {
+        let pending = !self.outgoing.is_empty();
+        if pending == self.writable_interest {
+            return;
+        }
+        let interest = if pending {Interest::READABLE | Interest::WRITABLE} else {Interest::READABLE};
+        poll.registry().reregister(&mut self.connection.stream, self.connection.token, interest);
+        self.writable_interest = pending;
+    }
+
+    //Decrypts the received data if the encryption is enabled and returns the packets that fully arrived
+    pub fn read_packets(&mut self, mut data: Vec<u8>) -> Result<Vec<RawPacket<'static>>, &'static str> {
+        match &mut self.decode {
+            Some(decode) => decode.decrypt(&mut data),
+            None => {}
+        }
+        self.connection.framer.feed(&data);
+        self.connection.framer.read_packets(self.compression_threshold.is_some())
+    }
+
     pub fn write_data_no_length(&mut self, data: &Vec<u8>) {
-        //Serialize
-        let mut data = data.clone();
-        //Encrypt
-        self.encode.encrypt(&mut data);
-        //Write
-        self.connection.stream.write(&data);
+        self.queue_raw(data.clone());
+        self.flush();
     }
 
     pub fn write_data(&mut self, data: &Vec<u8>) {
-        //Serialize
-        let mut data = data.clone();
-        //Add length prefix
-        data.splice(0..0, DataWriter::get_varint(data.len() as u32));
-        //Encrypt
-        self.encode.encrypt(&mut data);
-        //Write
-        self.connection.stream.write(&data);
+        match frame(data.clone(), self.compression_threshold) {
+            Ok(t) => self.queue_raw(t),
+            Err(e) => println!("Couldn't compress a packet to {}: {}", self.connection.identifier, e)
+        }
+        self.flush();
     }
 
     pub fn shutdown(&mut self, reason: String, poll: &Poll) {
@@ -120,13 +233,101 @@ impl PlayerClient {
     }
 
     pub fn shutdown_connection(&mut self, poll: &Poll) {
-        self.connection.stream.flush();
+        self.flush();
         poll.registry().deregister(&mut self.connection.stream);
         self.connection.stream.shutdown(Shutdown::Both);
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+//Packet already serialized and framed (length prefix and compression), shared by every client it's sent to
+#[derive(Clone, Debug, PartialEq)]
+pub struct SerializedPacket {
+    compression_threshold: Option<i32>,
+    data: Arc<Vec<u8>>
+}
+
+impl SerializedPacket {
+    pub fn new(packet: &Packet, compression_threshold: Option<i32>) -> Result<SerializedPacket, PacketError> {
+        let data = match frame(packet.serialize()?, compression_threshold) {
+            Ok(t) => t,
+            Err(e) => {
+                println!("Couldn't compress a packet: {}", e);
+                return Err(PacketError::Compression);
+            }
+        };
+        Ok(SerializedPacket {compression_threshold, data: Arc::new(data)})
+    }
+
+    //Framed for Play state clients, which all use the server's compression threshold
+    pub fn play(packet: &Packet) -> Result<SerializedPacket, PacketError> {
+        let threshold = if COMPRESSION_THRESHOLD >= 0 {Some(COMPRESSION_THRESHOLD)} else {None};
+        SerializedPacket::new(packet, threshold)
+    }
+}
+
+pub enum OutgoingPacket {
+    Packet(Packet),
+    Serialized(SerializedPacket)
+}
+
+impl From<Packet> for OutgoingPacket {
+    fn from(packet: Packet) -> Self {
+        OutgoingPacket::Packet(packet)
+    }
+}
+
+impl From<SerializedPacket> for OutgoingPacket {
+    fn from(packet: SerializedPacket) -> Self {
+        OutgoingPacket::Serialized(packet)
+    }
+}
+
+//Tracks the keep alive sent to a client and its response
+pub struct KeepAlive {
+    //Last time the client answered a keep alive with the right id
+    last_response: Instant,
+    //Id of the keep alive still waiting for the client's response
+    pending_id: Option<i32>
+}
+
+impl KeepAlive {
+    pub fn new(now: Instant) -> KeepAlive {
+        KeepAlive {last_response: now, pending_id: None}
+    }
+
+    //Returns a new random id to be sent, unless the last keep alive wasn't answered yet
+    pub fn next(&mut self) -> Option<i32> {
+        if self.pending_id.is_some() {
+            return None;
+        }
+        let id = thread_rng().gen::<i32>();
+        self.pending_id = Some(id);
+        Some(id)
+    }
+
+    //Only a response with the pending id counts
+    pub fn respond(&mut self, id: i32, now: Instant) {
+        if self.pending_id == Some(id) {
+            self.last_response = now;
+            self.pending_id = None;
+        }
+    }
+
+    pub fn timed_out(&self, now: Instant) -> bool {
+        now.duration_since(self.last_response) >= KEEP_ALIVE_TIMEOUT
+    }
+}
+
+//Builds the address to bind the server, IPv6 addresses are written without brackets (e.g. ::1)
+pub fn bind_address(ip: Option<&str>, port: Option<u16>) -> Result<SocketAddr, &'static str> {
+    let ip: IpAddr = match ip.unwrap_or(DEFAULT_IP).parse() {
+        Ok(t) => t,
+        Err(_) => return Err("Invalid bind ip address")
+    };
+    Ok(SocketAddr::new(ip, port.unwrap_or(DEFAULT_PORT)))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum ConnectionState {
     Handshaking,
     Status,
@@ -134,9 +335,9 @@ pub enum ConnectionState {
     Play
 }
 
-pub fn start(net_writer: Sender<GameProtocol>, net_reader: Receiver<NetProtocol>) {
+pub fn start(addr: SocketAddr, players: PlayerList, net_writer: Sender<GameProtocol>, net_reader: Receiver<NetProtocol>, offline_mode: bool) -> JoinHandle<()> {
     //Open server
-    let mut server = TcpListener::bind(ADDR.parse().unwrap()).expect("An error occured while binding the server");
+    let mut server = TcpListener::bind(addr).expect("An error occured while binding the server");
 
     //Initialize epoll
     let mut poll = Poll::new().expect("An error occured while initializing the epoll");
@@ -150,18 +351,36 @@ pub fn start(net_writer: Sender<GameProtocol>, net_reader: Receiver<NetProtocol>
     let mut play_clients: HashMap<Token, PlayerClient> = HashMap::new();
     let mut token_counter = 1usize;
 
-    println!("Waiting for connections on {}", ADDR);
+    //Authentication results from Mojang, answered in other threads
+    let (auth_sender, auth_receiver) = channel::<AuthResult>();
+
+    //Generated once and shared by every login client
+    let server_key = Arc::new(ServerKey::generate());
+
+    //Loaded once and shared too, the server runs without an icon if there's none
+    let favicon = if Path::new(favicon::FAVICON_PATH).exists() {
+        match favicon::load(favicon::FAVICON_PATH) {
+            Ok(t) => Some(Arc::new(t)),
+            Err(e) => {
+                println!("Couldn't load the server icon: {}", e);
+                None
+            }
+        }
+    } else {None};
+
+    println!("Waiting for connections on {}", addr);
+    if offline_mode {
+        println!("Running in offline mode, players won't be authenticated with Mojang");
+    }
 
     std::thread::Builder::new().name("IO Network Thread".to_string()).spawn(move || {
         let mut buffer = [0u8; BUFFER_SIZE];
-        unsafe {
-            let rsa = Rsa::generate(1024).expect("Couldn't generate RSA server key");
-            login_handler::PUBLIC_KEY = Some(rsa.public_key_to_der().expect("Couldn't generate RSA server public key"));
-            login_handler::RSA = Some(rsa);
-        }
 
         let mut last_keepalive = Instant::now();
 
+        //Set by a shutdown message, the loop stops after disconnecting everyone
+        let mut shutdown_reason: Option<ChatComponent> = None;
+
         loop {
             //Poll events
             poll.poll(&mut events, Some(Duration::from_millis(1)));
@@ -181,13 +400,20 @@ pub fn start(net_writer: Sender<GameProtocol>, net_reader: Receiver<NetProtocol>
                             //Got a client
                             Ok(mut client) => {
                                 let mut login_client = PlayerLoginClient {
-                                    connection: Connection { token: Token(token_counter), stream: client.0, addr: client.1, identifier: client.1.ip().to_string() },
+                                    connection: Connection { token: Token(token_counter), stream: client.0, addr: client.1, identifier: client.1.ip().to_string(), framer: PacketFramer::new() },
                                     state: ConnectionState::Handshaking,
                                     nickname: None,
                                     verify_token: None,
                                     encode: None,
                                     decode: None,
-                                    uuid: None
+                                    uuid: None,
+                                    properties: Vec::new(),
+                                    offline_mode,
+                                    compression_threshold: None,
+                                    key: server_key.clone(),
+                                    favicon: favicon.clone(),
+                                    players,
+                                    auth_sender: auth_sender.clone()
                                 };
 
                                 //Check if client is already logging
@@ -210,6 +436,13 @@ pub fn start(net_writer: Sender<GameProtocol>, net_reader: Receiver<NetProtocol>
                         }
                     }
                 } else {
+                    //The socket has space again for the data that didn't fit in the last flush
+                    if event.is_writable() {
+                        if let Some(client) = play_clients.get_mut(&token) {
+                            client.flush();
+                        }
+                    }
+
                     //Check for clients token
                     let mut login_client = login_clients.get_mut(&token);
                     let mut play_client = if login_client.is_none() {play_clients.get_mut(&token)} else {None};
@@ -283,43 +516,49 @@ pub fn start(net_writer: Sender<GameProtocol>, net_reader: Receiver<NetProtocol>
                         continue;
                     }
 
-                    //If it is play client, then decrypt the data first
-                    if let Some(ref mut client) = play_client {
-                        client.decode.decrypt(&mut vec);
+                    //Legacy ping isn't a normal packet, so answer it before reading the packets
+                    //The encryption is never enabled while handshaking, so the data doesn't need to be decrypted
+                    if let Some(client) = &mut login_client {
+                        if let ConnectionState::Handshaking = client.state {
+                            if login_handler::is_legacy_ping(&vec) {
+                                let online = client.players.lock().unwrap().len();
+                                client.connection.stream.write(&login_handler::legacy_ping_response(online));
+                                client.shutdown(String::new(), &poll);
+                                login_clients.remove(&token);
+                                continue;
+                            }
+                        }
                     }
 
-                    //Read packets length, id and separe them
-                    let raw_packets = match read_packets(&vec) {
-                        Some(t) => t,
-                        None => { continue; }
+                    //Decrypt, read packets length, id and separe them
+                    let read = match (&mut login_client, &mut play_client) {
+                        (Some(client), _) => client.read_packets(vec),
+                        (_, Some(client)) => client.read_packets(vec),
+                        _ => continue
+                    };
+                    let raw_packets = match read {
+                        Ok(t) => t,
+                        Err(e) => {
+                            if let Some(client) = play_client {
+                                println!("Disconnected {}: {}", client.connection.identifier, e);
+                                client.shutdown(e.to_string(), &poll);
+                                net_writer.send(GameProtocol::ForcedDisconnect {token, reason: IOError});
+                                play_clients.remove(&token);
+                            } else {
+                                let client = login_client.unwrap();
+                                println!("Disconnected {}: {}", client.connection.identifier, e);
+                                client.shutdown(e.to_string(), &poll);
+                                login_clients.remove(&token);
+                            }
+                            continue;
+                        }
                     };
 
                     //Handle the login
                     match login_client {
                         Some(client) => {
                             let result = login_handler::handle(raw_packets, client);
-                            match result {
-                                HandleResult::Disconnect(reason) => {
-                                    client.shutdown(reason.to_string(), &poll);
-                                    login_clients.remove(&token);
-                                    break;
-                                }
-                                HandleResult::Login => {
-                                    //Player is ready to go to Play connection state
-                                    let client = login_clients.remove(&token).unwrap();
-
-                                    let play_client = PlayerClient {
-                                        connection: client.connection,
-                                        encode: client.encode.unwrap(),
-                                        decode: client.decode.unwrap(),
-                                        keep_alive: now
-                                    };
-
-                                    play_clients.insert(play_client.connection.token, play_client);
-                                    net_writer.send(GameProtocol::Login {token, uuid: client.uuid.unwrap(), nickname: client.nickname.unwrap()});
-                                }
-                                HandleResult::None => {}
-                            }
+                            login_result(token, result, &mut login_clients, &mut play_clients, &poll, &net_writer, now);
                             continue;
                         },
                         _ => {}
@@ -329,16 +568,24 @@ pub fn start(net_writer: Sender<GameProtocol>, net_reader: Receiver<NetProtocol>
                     match play_client {
                         Some(player) => {
                             for raw_packet in raw_packets {
-                                let packet = Packet::read(raw_packet.id, &mut DataReader::new(raw_packet.data), ConnectionState::Play);
+                                let packet = Packet::read(raw_packet.id, &mut DataReader::new(&raw_packet.data), ConnectionState::Play);
                                 match packet {
-                                    Some(packet) => {
+                                    Ok(packet) => {
                                         //Send packets to be processed by the tick thread
                                         match packet {
-                                            Packet::KeepAlive {id} => player.keep_alive = now,
+                                            Packet::KeepAlive {id} => player.keep_alive.respond(id, now),
                                             _ => {net_writer.send(GameProtocol::Packet { token, packet });}
                                         };
                                     }
-                                    None => {}
+                                    //Unknown ids are only logged, the server doesn't implement every packet yet
+                                    Err(e @ PacketError::UnknownId {..}) => println!("Invalid packet from {}: {}", player.connection.identifier, e),
+                                    Err(e) => {
+                                        println!("Invalid packet from {}: {}", player.connection.identifier, e);
+                                        player.shutdown(e.reason().to_string(), &poll);
+                                        net_writer.send(GameProtocol::ForcedDisconnect {token, reason: IOError});
+                                        play_clients.remove(&token);
+                                        break;
+                                    }
                                 }
                             }
                         }
@@ -347,12 +594,28 @@ pub fn start(net_writer: Sender<GameProtocol>, net_reader: Receiver<NetProtocol>
                 }
             }
             
+            //Clients authenticated by Mojang (or not) since the last iteration
+            for auth in auth_receiver.try_iter() {
+                //The client may have disconnected while waiting
+                let client = match login_clients.get_mut(&auth.token) {Some(t) => t, None => continue};
+                let result = login_handler::finish_authentication(client, auth.profile);
+                login_result(auth.token, result, &mut login_clients, &mut play_clients, &poll, &net_writer, now);
+            }
+
             //Starts to read the game messages
             for message in net_reader.try_iter() {
                 match message {
                     NetProtocol::SendPacket {token, packet} => {
                         let client = match play_clients.get_mut(&token) {Some(t) => t, None => continue};
-                        client.write(packet);
+                        if let Err(e) = client.queue(packet) {
+                            println!("Couldn't send a packet to {}: {}", client.connection.identifier, e);
+                        }
+                    }
+                    NetProtocol::SendSerialized {token, packet} => {
+                        let client = match play_clients.get_mut(&token) {Some(t) => t, None => continue};
+                        if let Err(e) = client.queue(packet) {
+                            println!("Couldn't send a packet to {}: {}", client.connection.identifier, e);
+                        }
                     }
                     NetProtocol::SendData {token, packet} => {
                         let client = match play_clients.get_mut(&token) {Some(t) => t, None => continue};
@@ -367,15 +630,29 @@ pub fn start(net_writer: Sender<GameProtocol>, net_reader: Receiver<NetProtocol>
                         client.shutdown_connection(&poll);
                         play_clients.remove(&token);
                     }
+                    NetProtocol::Shutdown {reason} => shutdown_reason = Some(reason)
                 }
             }
 
+            //Packets sent by the game in this iteration (e.g. all the join packets) go in a single write
+            for client in play_clients.values_mut() {
+                client.flush();
+                client.update_interest(&poll);
+            }
+
+            if let Some(reason) = shutdown_reason.take() {
+                disconnect_all(&mut login_clients, &mut play_clients, reason, &poll);
+                poll.registry().deregister(&mut server);
+                println!("Server closed");
+                break;
+            }
+
             if send_keepalive {
                 last_keepalive = now;
 
                 //Check for clients who are taking too long to send the keep alive
                 play_clients.retain(|token, player| {
-                    if now.duration_since(player.keep_alive).as_secs() >= 10 {
+                    if player.keep_alive.timed_out(now) {
                         //Timeout disconnect
                         player.shutdown("Timeout".to_string(), &poll);
                         net_writer.send(GameProtocol::ForcedDisconnect {token: *token, reason: Timeout });
@@ -385,14 +662,57 @@ pub fn start(net_writer: Sender<GameProtocol>, net_reader: Receiver<NetProtocol>
                     true
                 });
 
-                //Send keep alive packets
-                let keep_alive = Packet::KeepAlive {id: 0}.serialize_length().unwrap();
+                //Send keep alive packets to the clients who already answered the last one
                 for player in play_clients.values_mut() {
-                    player.write_data_no_length(&keep_alive);
+                    if let Some(id) = player.keep_alive.next() {
+                        player.write(Packet::KeepAlive {id});
+                    }
                 }
             }
         }
-    });
+    }).unwrap()
+}
+
+//Moves a client that finished the login to the Play state, or drops it if it was disconnected
+fn login_result(token: Token, result: HandleResult, login_clients: &mut HashMap<Token, PlayerLoginClient>, play_clients: &mut HashMap<Token, PlayerClient>, poll: &Poll, net_writer: &Sender<GameProtocol>, now: Instant) {
+    match result {
+        HandleResult::Disconnect(reason) => {
+            //The login handler already sent the reason and closed the socket
+            let mut client = match login_clients.remove(&token) {Some(t) => t, None => return};
+            println!("Disconnected {} while logging in: {}", client.connection.identifier, reason);
+            poll.registry().deregister(&mut client.connection.stream);
+        }
+        HandleResult::Login => {
+            //Player is ready to go to Play connection state
+            let client = match login_clients.remove(&token) {Some(t) => t, None => return};
+
+            let play_client = PlayerClient {
+                connection: client.connection,
+                encode: client.encode,
+                decode: client.decode,
+                compression_threshold: client.compression_threshold,
+                keep_alive: KeepAlive::new(now),
+                outgoing: Vec::new(),
+                writable_interest: false
+            };
+
+            play_clients.insert(play_client.connection.token, play_client);
+            net_writer.send(GameProtocol::Login {token, uuid: client.uuid.unwrap(), nickname: client.nickname.unwrap(), properties: client.properties});
+        }
+        HandleResult::None => {}
+    }
+}
+
+//Sends the reason to every client and closes their sockets, login clients get it only if they're already in Login state
+fn disconnect_all(login_clients: &mut HashMap<Token, PlayerLoginClient>, play_clients: &mut HashMap<Token, PlayerClient>, reason: ChatComponent, poll: &Poll) {
+    for (_, mut client) in login_clients.drain() {
+        poll.registry().deregister(&mut client.connection.stream);
+        client.disconnect(reason.clone());
+    }
+    for (_, mut player) in play_clients.drain() {
+        player.write(Packet::DisconnectPlay {reason: reason.clone()});
+        player.shutdown_connection(poll);
+    }
 }
 
 pub enum NetProtocol {
@@ -400,6 +720,10 @@ pub enum NetProtocol {
         token: Token,
         packet: Packet
     },
+    SendSerialized {
+        token: Token,
+        packet: SerializedPacket
+    },
     SendData {
         token: Token,
         packet: Arc<Vec<u8>>
@@ -410,6 +734,10 @@ pub enum NetProtocol {
     },
     Unregister {
         token: Token
+    },
+    //Disconnects every client with the reason and stops the network thread
+    Shutdown {
+        reason: ChatComponent
     }
 }
 
@@ -417,7 +745,8 @@ pub enum GameProtocol {
     Login {
         token: Token,
         nickname: String,
-        uuid: Uuid
+        uuid: Uuid,
+        properties: Vec<PlayerInfoProperties>
     },
     ForcedDisconnect {
         token: Token,
@@ -455,6 +784,28 @@ impl NetWriter {
         self.writer.send(NetProtocol::SendPacket {token, packet: Packet::DisconnectPlay {reason}});
         self.writer.send(NetProtocol::Unregister {token});
     }
+
+    //Serializes the packet once and sends the same data to every player
+    pub fn broadcast(&self, players: &[Player], packet: Packet) -> Result<(), PacketError> {
+        let packet = SerializedPacket::play(&packet)?;
+        for player in players {
+            self.writer.send(NetProtocol::SendSerialized {token: player.token, packet: packet.clone()});
+        }
+        Ok(())
+    }
+
+    //Same as broadcast, but skips the excluded player (e.g. the one who sent a chat message)
+    pub fn broadcast_except(&self, players: &[Player], exclude: Token, packet: Packet) -> Result<(), PacketError> {
+        let packet = SerializedPacket::play(&packet)?;
+        for player in players.iter().filter(|player| player.token != exclude) {
+            self.writer.send(NetProtocol::SendSerialized {token: player.token, packet: packet.clone()});
+        }
+        Ok(())
+    }
+
+    pub fn shutdown(&self, reason: ChatComponent) {
+        self.writer.send(NetProtocol::Shutdown {reason});
+    }
 }
 
 impl Clone for NetWriter {
@@ -465,54 +816,265 @@ impl Clone for NetWriter {
 
 pub struct RawPacket<'a> {
     pub id: i32,
-    pub data: &'a [u8]
+    //Owned when the packet had to be decompressed
+    pub data: Cow<'a, [u8]>
 }
 
-fn read_varint<'a>(slice: &[u8], index: &mut usize) -> Option<i32> {
-    let mut result: i32 = 0;
-    let mut read: u8;
-    for i in 0..5 {
-        read = *slice.get(i)?;
-        result += (((read as i8) & 0b01111111) as i32) << (7 * i);
-        *index += 1;
-
-        if (read & 0b10000000) == 0 {
-            return Some(result)
+//Adds the length prefix to a serialized packet, using the compressed format if compression is enabled
+fn frame(mut data: Vec<u8>, compression_threshold: Option<i32>) -> io::Result<Vec<u8>> {
+    match compression_threshold {
+        Some(threshold) => compression::compress(&data, threshold),
+        None => {
+            data.splice(0..0, DataWriter::get_varint(data.len() as u32));
+            Ok(data)
         }
     }
+}
 
-    return None;
+//Test client in Play state connected to a local socket, the peer receives everything the client writes
+#[cfg(test)]
+pub fn play_client_pair(token: Token) -> (PlayerClient, std::net::TcpStream) {
+    let (connection, peer) = connection_pair(token);
+    let client = PlayerClient {
+        connection,
+        encode: None,
+        decode: None,
+        compression_threshold: None,
+        keep_alive: KeepAlive::new(Instant::now()),
+        outgoing: Vec::new(),
+        writable_interest: false
+    };
+    (client, peer)
 }
 
-fn read_packets(data: &Vec<u8>) -> Option<Vec<RawPacket>> {
-    let mut raw_packets = Vec::new();
-    let mut index = 0usize;
-    while index < data.len() {
-        //Check if it has no space for packet length reading
-        if index >= data.len() {
-            println!("Index bigger 1 {} {} {:?}", index, data.len(), data);
-            return None
-        };
-        let mut length = read_varint(&data[index..], &mut index)? as usize;
-        let mut id_length = 0usize;
+//Test client in Login state that already sent its nickname and got the verify token 1, 2, 3, 4
+#[cfg(test)]
+pub fn login_client_pair(key: Arc<ServerKey>) -> (PlayerLoginClient, std::net::TcpStream) {
+    let (connection, peer) = connection_pair(Token(1));
+    let client = PlayerLoginClient {
+        connection,
+        state: ConnectionState::Login,
+        nickname: Some("MrPiva".to_string()),
+        verify_token: Some([1, 2, 3, 4]),
+        encode: None,
+        decode: None,
+        uuid: None,
+        properties: Vec::new(),
+        offline_mode: false,
+        compression_threshold: None,
+        key,
+        favicon: None,
+        players: Box::leak(Box::new(std::sync::Mutex::new(Vec::new()))),
+        auth_sender: channel().0
+    };
+    (client, peer)
+}
 
-        //Check if it has no space for id length reading
-        if index >= data.len() {
-            println!("Index bigger 2 {} {} {:?}", index, data.len(), data);
-            return None
-        };
-        let id = read_varint(&data[index..], &mut id_length)?;
-        index += id_length as usize;
-        length -= id_length;
-
-        //Check if it has no space for reading the rest of the packet
-        if index + length > data.len() {
-            println!("Bigger {} {} {} {:?}", index, length, data.len(), data);
-            return None
-        };
-        raw_packets.push(RawPacket {id, data: &data[index..index + length]});
-        index += length;
+#[cfg(test)]
+fn connection_pair(token: Token) -> (Connection, std::net::TcpStream) {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let peer = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, addr) = listener.accept().unwrap();
+    stream.set_nonblocking(true).unwrap();
+    let connection = Connection {token, stream: TcpStream::from_std(stream), addr, identifier: addr.ip().to_string(), framer: PacketFramer::new()};
+    (connection, peer)
+}
+
+#[test]
+pub fn test_encryption() {
+    use aes::cipher::NewStreamCipher;
+
+    let shared_secret = [0x2Au8; 16];
+    let cipher = || Some(Cfb8::<Aes128>::new_var(&shared_secret, &shared_secret).unwrap());
+    let key = Arc::new(ServerKey::generate());
+
+    //The server encrypts the Login Success it writes
+    let (mut client, mut peer) = login_client_pair(key.clone());
+    client.encode = cipher();
+    let packet = Packet::LoginSuccess {uuid: Uuid::new_v4(), nickname: "MrPiva".to_string()};
+    let frame = packet.serialize_length().unwrap();
+    client.write(packet);
+    client.connection.stream.shutdown(Shutdown::Write).unwrap();
+    let mut data = Vec::new();
+    peer.read_to_end(&mut data).unwrap();
+    assert_eq!(data.len(), frame.len());
+    assert_ne!(data, frame);
+
+    //And a client with the same shared secret reads it back, even if the stream arrives split in any way
+    let (mut reader, _peer) = login_client_pair(key);
+    reader.decode = cipher();
+    let mut packets = Vec::new();
+    for chunk in data.chunks(3) {
+        packets.extend(reader.read_packets(chunk.to_vec()).unwrap());
+    }
+    assert_eq!(packets.len(), 1);
+    assert_eq!(packets[0].id, 0x02);
+    assert_eq!(&*packets[0].data, &frame[2..]);
+}
+
+#[test]
+pub fn test_keep_alive_timeout() {
+    let start = Instant::now();
+    let mut keep_alive = KeepAlive::new(start);
+
+    let id = keep_alive.next().unwrap();
+    //A wrong id doesn't count as an answer, and no new keep alive is sent while waiting
+    keep_alive.respond(id.wrapping_add(1), start + Duration::from_secs(1));
+    assert!(keep_alive.next().is_none());
+    assert!(!keep_alive.timed_out(start + Duration::from_secs(29)));
+    assert!(keep_alive.timed_out(start + KEEP_ALIVE_TIMEOUT));
+
+    //Answering with the right id resets the timeout
+    keep_alive.respond(id, start + Duration::from_secs(2));
+    assert!(!keep_alive.timed_out(start + KEEP_ALIVE_TIMEOUT));
+    assert!(keep_alive.next().is_some());
+}
+
+#[test]
+pub fn test_bind_address() {
+    assert_eq!(bind_address(None, None).unwrap(), "0.0.0.0:25565".parse().unwrap());
+    assert_eq!(bind_address(Some("127.0.0.1"), Some(25570)).unwrap(), "127.0.0.1:25570".parse().unwrap());
+
+    let ipv6 = bind_address(Some("::1"), Some(25566)).unwrap();
+    assert!(ipv6.is_ipv6());
+    assert_eq!(ipv6.port(), 25566);
+
+    assert!(bind_address(Some("localhost"), None).is_err());
+}
+
+#[test]
+pub fn test_disconnect_all() {
+    let poll = Poll::new().unwrap();
+    let mut play_clients = HashMap::new();
+    let mut peers = Vec::new();
+
+    for i in 1..=3 {
+        let (client, peer) = play_client_pair(Token(i));
+        play_clients.insert(Token(i), client);
+        peers.push(peer);
+    }
+
+    let reason = ChatComponent::new_text("Server closing".to_string());
+    disconnect_all(&mut HashMap::new(), &mut play_clients, reason.clone(), &poll);
+    assert!(play_clients.is_empty());
+
+    let expected = Packet::DisconnectPlay {reason}.serialize_length().unwrap();
+    for mut peer in peers {
+        let mut data = Vec::new();
+        peer.read_to_end(&mut data).unwrap();
+        assert_eq!(data, expected);
+    }
+}
+
+#[test]
+pub fn test_broadcast() {
+    use std::sync::mpsc::channel;
+
+    let (sender, receiver) = channel();
+    let net_writer = NetWriter {writer: sender};
+    let players: Vec<Player> = (1..=3).map(|i| Player {token: Token(i), uuid: Uuid::new_v4(), nickname: format!("Player{}", i), properties: vec!()}).collect();
+
+    let packet = || Packet::TimeUpdate {world_age: 0, time_of_day: 6000};
+    let expected = SerializedPacket::play(&packet()).unwrap();
+
+    //Every player gets the same serialized data
+    net_writer.broadcast(&players, packet()).unwrap();
+    let sent: Vec<(Token, SerializedPacket)> = receiver.try_iter().map(|message| match message {
+        NetProtocol::SendSerialized {token, packet} => (token, packet),
+        _ => panic!("Broadcast should send serialized data")
+    }).collect();
+    assert_eq!(sent.iter().map(|(token, _)| *token).collect::<Vec<_>>(), vec![Token(1), Token(2), Token(3)]);
+    assert_eq!(sent[0].1, expected);
+
+    net_writer.broadcast_except(&players, Token(2), packet()).unwrap();
+    let tokens: Vec<Token> = receiver.try_iter().map(|message| match message {
+        NetProtocol::SendSerialized {token, ..} => token,
+        _ => panic!("Broadcast should send serialized data")
+    }).collect();
+    assert_eq!(tokens, vec![Token(1), Token(3)]);
+}
+
+#[test]
+pub fn test_broadcast_serializes_once() {
+    use std::sync::mpsc::channel;
+
+    let (sender, receiver) = channel();
+    let net_writer = NetWriter {writer: sender};
+    let players: Vec<Player> = (1..=500).map(|i| Player {token: Token(i), uuid: Uuid::new_v4(), nickname: format!("Player{}", i), properties: vec!()}).collect();
+
+    //A packet big enough to be compressed
+    net_writer.broadcast(&players, Packet::ChatMessage {component: ChatComponent::new_text("a".repeat(1000)), position: 0}).unwrap();
+    let sent: Vec<SerializedPacket> = receiver.try_iter().map(|message| match message {
+        NetProtocol::SendSerialized {packet, ..} => packet,
+        _ => panic!("Broadcast should send serialized data")
+    }).collect();
+
+    //Every client shares the buffer framed a single time, instead of serializing and compressing 500 times
+    assert_eq!(sent.len(), 500);
+    assert!(sent.iter().all(|packet| Arc::ptr_eq(&packet.data, &sent[0].data)));
+    assert_eq!(Arc::strong_count(&sent[0].data), 500);
+}
+
+#[test]
+pub fn test_queue_flush() {
+    use aes::cipher::NewStreamCipher;
+
+    let shared_secret = [0x2Au8; 16];
+    let (mut client, mut peer) = play_client_pair(Token(1));
+    client.encode = Some(Cfb8::<Aes128>::new_var(&shared_secret, &shared_secret).unwrap());
+
+    let packets = vec![
+        Packet::KeepAlive {id: 1},
+        Packet::TimeUpdate {world_age: 0, time_of_day: 6000},
+        Packet::KeepAlive {id: 2}
+    ];
+    let mut expected: Vec<u8> = packets.iter().flat_map(|packet| packet.serialize_length().unwrap()).collect();
+    for packet in packets {
+        client.queue(packet).unwrap();
+    }
+
+    //Encrypting the combined buffer with a fresh cipher gives the same bytes as encrypting each packet
+    Cfb8::<Aes128>::new_var(&shared_secret, &shared_secret).unwrap().encrypt(&mut expected);
+    assert_eq!(client.outgoing, expected);
+
+    client.flush();
+    assert!(client.outgoing.is_empty());
+    client.connection.stream.shutdown(Shutdown::Write).unwrap();
+
+    let mut data = Vec::new();
+    peer.read_to_end(&mut data).unwrap();
+    assert_eq!(data, expected);
+}
+
+#[test]
+pub fn test_flush_full_socket() {
+    let (mut client, peer) = play_client_pair(Token(1));
+
+    //Way more than the socket buffers can hold while the peer isn't reading
+    let packet = || Packet::ChunkData {chunk_x: 0, chunk_z: 0, ground_up_continuous: true, primary_bitmask: 0xFFFF, data: vec![7; 65536]};
+    let mut expected = Vec::new();
+    for _ in 0..256 {
+        expected.extend(packet().serialize_length().unwrap());
+        client.queue(packet()).unwrap();
+    }
+
+    //The first flush fills the window, the rest must stay queued
+    client.flush();
+    assert!(!client.outgoing.is_empty());
+    assert!(client.outgoing.len() < expected.len());
+
+    let reader = std::thread::spawn(move || {
+        let mut peer = peer;
+        let mut data = Vec::new();
+        peer.read_to_end(&mut data).unwrap();
+        data
+    });
+    while !client.outgoing.is_empty() {
+        client.flush();
+        std::thread::sleep(Duration::from_millis(1));
     }
+    client.connection.stream.shutdown(Shutdown::Write).unwrap();
 
-    Some(raw_packets)
+    //Nothing was lost, so an encrypted stream would have stayed in sync too
+    assert!(reader.join().unwrap() == expected);
 }
\ No newline at end of file